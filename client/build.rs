@@ -0,0 +1,19 @@
+use std::env;
+
+/// Asset packs are only trusted when signed with this key. Clients built without it still
+/// update from git, but refuse pack sources.
+const PACK_PUBLIC_KEY_VAR: &str = "GV_PACK_PUBLIC_KEY";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed={}", PACK_PUBLIC_KEY_VAR);
+    let key = env::var(PACK_PUBLIC_KEY_VAR).unwrap_or_default();
+    if !key.is_empty() && !key.trim_start().starts_with("-----BEGIN PUBLIC KEY-----") {
+        panic!("{} has to be a PEM public key", PACK_PUBLIC_KEY_VAR);
+    }
+    if key.is_empty() && env::var("PROFILE").is_ok_and(|profile| profile == "release") {
+        println!(
+            "cargo:warning={} isn't set; this client can't update from asset packs",
+            PACK_PUBLIC_KEY_VAR
+        );
+    }
+}
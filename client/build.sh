@@ -1,5 +1,13 @@
 #!/bin/bash
 
+# Asset packs have to be signed with this key, made as described in gv-pack
+if [ -z "$GV_PACK_PUBLIC_KEY" ] && [ -f ../certs/pack_key.pub.pem ]; then
+    export GV_PACK_PUBLIC_KEY="$(cat ../certs/pack_key.pub.pem)"
+fi
+if [ -z "$GV_PACK_PUBLIC_KEY" ]; then
+    echo "GV_PACK_PUBLIC_KEY isn't set and there's no certs/pack_key.pub.pem; the client won't accept asset packs" >&2
+fi
+
 rm ./out/*
 
 rm -R ./build_deps
@@ -1,36 +1,295 @@
-use git2::{build::CheckoutBuilder, Repository};
+use std::{
+    fmt, fs, io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use common::assets::{
+    AssetKind, AssetManifest, AssetSource, ManifestError, CONTENT_FILES, MANIFEST_FILE,
+};
+use git2::{build::CheckoutBuilder, BranchType, Commit, FetchOptions, RemoteCallbacks, Repository};
 use tracing::info;
 
-pub fn update_assets(assets_path: &str) -> Result<(), git2::Error> {
-    // Define the repository URL
-    let repo_url = "https://github.com/DarkCoder28/CIS4000_Capstone-Spring2024-ASSETS.git"; // Replace with your GitHub repository URL
+use crate::pack_updater::update_pack;
+
+/// Environment variable setting the directory assets are kept in.
+pub const ASSET_PATH_VAR: &str = "GV_ASSET_PATH";
+
+/// Whether `assets_path` holds assets the game can start from, without fetching anything.
+pub fn has_local_assets(assets_path: &str) -> bool {
+    CONTENT_FILES
+        .iter()
+        .all(|file| Path::new(assets_path).join(file).is_file())
+}
+
+#[derive(Debug)]
+pub enum UpdateError {
+    Cancelled,
+    Git(git2::Error),
+    Download(String),
+    Io(io::Error),
+    Manifest(ManifestError),
+    /// A downloaded file didn't match the manifest's hash.
+    Corrupt(String),
+    /// The pack wasn't built from the commit the source pins.
+    WrongVersion(String),
+    /// The client was built without `GV_PACK_PUBLIC_KEY`, so it can't trust any pack.
+    NoPublicKey,
+    /// This many files were still broken after repairing.
+    Unrepaired(usize),
+    /// Restoring the files needs downloading them, but the game is running offline.
+    Offline,
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::Cancelled => write!(f, "cancelled"),
+            UpdateError::Git(e) => write!(f, "{}", e),
+            UpdateError::Download(e) => write!(f, "couldn't download {}", e),
+            UpdateError::Io(e) => write!(f, "{}", e),
+            UpdateError::Manifest(e) => write!(f, "bad manifest: {}", e),
+            UpdateError::Corrupt(path) => write!(f, "'{}' doesn't match the manifest", path),
+            UpdateError::WrongVersion(commit) => {
+                write!(f, "the pack wasn't built from commit {}", commit)
+            }
+            UpdateError::NoPublicKey => write!(f, "this client can't verify asset packs"),
+            UpdateError::Unrepaired(count) => write!(f, "{} files couldn't be repaired", count),
+            UpdateError::Offline => write!(f, "can't download assets while offline"),
+        }
+    }
+}
+
+impl From<io::Error> for UpdateError {
+    fn from(e: io::Error) -> Self {
+        UpdateError::Io(e)
+    }
+}
+
+impl From<ManifestError> for UpdateError {
+    fn from(e: ManifestError) -> Self {
+        UpdateError::Manifest(e)
+    }
+}
 
-    // Open the repository or clone it if it doesn't exist
+/// How far an asset update has got, shared with the thread running it.
+#[derive(Default)]
+pub struct UpdateProgress {
+    received_objects: AtomicUsize,
+    total_objects: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl UpdateProgress {
+    /// The share of objects fetched so far, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        let total = self.total_objects.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.;
+        }
+        self.received_objects.load(Ordering::Relaxed) as f32 / total as f32
+    }
+
+    /// Records that `received` of `total` objects or files have been fetched.
+    pub fn report(&self, received: usize, total: usize) {
+        self.received_objects.store(received, Ordering::Relaxed);
+        self.total_objects.store(total, Ordering::Relaxed);
+    }
+
+    /// Stops the fetch at its next progress report, leaving the checked out assets as they
+    /// were.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// An asset update running on its own thread so the window keeps drawing.
+pub struct AssetUpdate {
+    progress: Arc<UpdateProgress>,
+    thread: Option<JoinHandle<Result<(), UpdateError>>>,
+}
+
+impl AssetUpdate {
+    pub fn start(assets_path: &str, source: &AssetSource) -> Self {
+        let assets_path = assets_path.to_string();
+        let source = source.clone();
+        AssetUpdate::spawn(move |progress| update(&assets_path, &source, progress))
+    }
+
+    /// Runs `work` on its own thread, passing it the progress to report to.
+    pub fn spawn(
+        work: impl FnOnce(&UpdateProgress) -> Result<(), UpdateError> + Send + 'static,
+    ) -> Self {
+        let progress = Arc::new(UpdateProgress::default());
+        let thread = {
+            let progress = progress.clone();
+            thread::spawn(move || work(&progress))
+        };
+        AssetUpdate {
+            progress,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn progress(&self) -> &UpdateProgress {
+        &self.progress
+    }
+
+    /// The update's result once it has finished.
+    pub fn poll(&mut self) -> Option<Result<(), UpdateError>> {
+        if !self.thread.as_ref()?.is_finished() {
+            return None;
+        }
+        let result = self.thread.take()?.join();
+        Some(
+            result.unwrap_or_else(|_| {
+                Err(UpdateError::Io(io::Error::other("Asset update panicked")))
+            }),
+        )
+    }
+}
+
+/// Brings the assets at `assets_path` up to date with `source`, however it's delivered.
+pub fn update(
+    assets_path: &str,
+    source: &AssetSource,
+    progress: &UpdateProgress,
+) -> Result<(), UpdateError> {
+    let result = match source.kind {
+        AssetKind::Git => update_assets(assets_path, source, progress).map_err(UpdateError::Git),
+        AssetKind::Pack => update_pack(assets_path, source, progress),
+    };
+    match result {
+        Err(_) if progress.is_cancelled() => Err(UpdateError::Cancelled),
+        result => result,
+    }
+}
+
+/// Brings the assets at `assets_path` up to date with `source`, downloading them if needed.
+///
+/// The repository is set up before anything is fetched, so an update that's cancelled or
+/// cut off leaves a repository the next update fetches into rather than a half-cloned
+/// directory, and the checked out assets are only touched once the fetch is done.
+pub fn update_assets(
+    assets_path: &str,
+    source: &AssetSource,
+    progress: &UpdateProgress,
+) -> Result<(), git2::Error> {
+    // Open the repository or create it if it doesn't exist
     let repo = match Repository::open(assets_path) {
         Ok(repo) => repo,
         Err(_) => {
-            info!("Repository doesn't exist locally. Cloning...");
-            Repository::clone(repo_url, assets_path)?
+            info!(
+                "Repository doesn't exist locally. Downloading {}...",
+                source.url
+            );
+            Repository::init(assets_path)?
         }
     };
 
-    // Get the remote named "origin"
+    // Point "origin" at the configured source
+    match repo.find_remote("origin") {
+        Ok(remote) => {
+            if remote.url() != Some(source.url.as_str()) {
+                info!("Switching asset source to {}", source.url);
+                repo.remote_set_url("origin", &source.url)?;
+            }
+        }
+        Err(_) => {
+            repo.remote("origin", &source.url)?;
+        }
+    }
     let mut remote = repo.find_remote("origin")?;
 
-    // Fetch the latest changes
-    let refspecs = ["refs/heads/*:refs/heads/*"];
-    remote.fetch(&refspecs, None, None)?;
+    // Report the transfer, aborting it once the update is cancelled
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.transfer_progress(|stats| {
+        progress.report(stats.received_objects(), stats.total_objects());
+        !progress.is_cancelled()
+    });
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
 
-    // Get the remote branch to compare against
-    let remote_branch_name = "origin/master";
-    let remote_branch = repo.find_branch(remote_branch_name, git2::BranchType::Remote)?;
-    let remote_commit = remote_branch.get().peel_to_commit()?;
+    // Fetch the latest changes and find the commit to check out
+    let mut remote_commit = match &source.tag {
+        Some(tag) => {
+            let refspec = format!("+refs/tags/{0}:refs/tags/{0}", tag);
+            remote.fetch(&[refspec], Some(&mut fetch_options), None)?;
+            repo.revparse_single(&format!("refs/tags/{}", tag))?
+                .peel_to_commit()?
+        }
+        None => {
+            let refspec = format!("+refs/heads/{0}:refs/remotes/origin/{0}", source.branch);
+            remote.fetch(&[refspec], Some(&mut fetch_options), None)?;
+            let remote_branch_name = format!("origin/{}", source.branch);
+            let remote_branch = repo.find_branch(&remote_branch_name, BranchType::Remote)?;
+            remote_branch.get().peel_to_commit()?
+        }
+    };
+    if let Some(commit) = &source.commit {
+        remote_commit = repo.revparse_single(commit)?.peel_to_commit()?;
+    }
 
     // Reset the local branch to match the remote
     let obj = repo.find_object(remote_commit.id(), None)?;
-    repo.reset(&obj, git2::ResetType::Hard, CheckoutBuilder::new().force().into())?;//Some(&mut branch_ref.name()))?;
+    repo.reset(
+        &obj,
+        git2::ResetType::Hard,
+        CheckoutBuilder::new().force().into(),
+    )?;
 
-    println!("Local branch reset to match remote");
+    info!("Assets reset to {} of {}", source.reference(), source.url);
 
     Ok(())
 }
+
+/// The commit the assets at `assets_path` are checked out at, or the one the pack they came
+/// from was built from.
+pub fn checked_out_commit(assets_path: &str) -> Option<String> {
+    let Ok(repo) = Repository::open(assets_path) else {
+        return local_manifest(assets_path)?.version;
+    };
+    let head = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(head.id().to_string())
+}
+
+/// The manifest of the pack the assets at `assets_path` were last downloaded from.
+pub fn local_manifest(assets_path: &str) -> Option<AssetManifest> {
+    let data = fs::read(Path::new(assets_path).join(MANIFEST_FILE)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Whether the checked out assets are at `commit`, or only differ from it in files that
+/// don't affect the player's progress. Assets from packs have to be at `commit`.
+pub fn is_compatible(assets_path: &str, commit: &str) -> Result<bool, git2::Error> {
+    if Repository::open(assets_path).is_err() && local_manifest(assets_path).is_some() {
+        return Ok(
+            checked_out_commit(assets_path).is_some_and(|version| version.starts_with(commit))
+        );
+    }
+    let repo = Repository::open(assets_path)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let pinned = repo.revparse_single(commit)?.peel_to_commit()?;
+    if head.id() == pinned.id() {
+        return Ok(true);
+    }
+    for file in CONTENT_FILES {
+        if content_id(&head, file) != content_id(&pinned, file) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn content_id(commit: &Commit, file: &str) -> Option<git2::Oid> {
+    let entry = commit.tree().ok()?.get_path(Path::new(file)).ok()?;
+    Some(entry.id())
+}
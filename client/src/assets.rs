@@ -0,0 +1,284 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
+
+use common::{map_data::MapMeta, map_objects::MapObjects};
+use macroquad::prelude::*;
+use macroquad_tiled as tiled;
+
+/// Seconds of each frame spent loading queued assets.
+const FRAME_BUDGET: f64 = 1. / 120.;
+
+/// A Tiled map with its tilesets loaded and its objects read.
+pub struct MapAssets {
+    pub tiled_map: tiled::Map,
+    pub map_objects: MapObjects,
+}
+
+#[derive(Clone)]
+enum Asset {
+    Texture(Texture2D),
+    Map(Rc<MapAssets>),
+}
+
+enum Load {
+    Texture,
+    /// A map's JSON, loaded after the tilesets it references by name.
+    Map {
+        loc_id: String,
+        tilesets: Vec<(String, String)>,
+    },
+}
+
+enum Status {
+    Queued(Load),
+    Ready(Asset),
+    Failed,
+}
+
+struct Entry {
+    status: Status,
+    /// Scenes and maps using the asset.
+    refs: usize,
+    /// Kept around without references because the player is likely to need it next.
+    preload: bool,
+    /// Assets this one holds a reference to.
+    deps: Vec<String>,
+}
+
+/// Textures and maps shared between scenes, keyed by path.
+///
+/// Scenes take a reference when they're entered and release it when they exit; assets are
+/// dropped once nothing references them unless they're being preloaded. Queued assets load
+/// a few at a time between frames so loading screens and preloading don't block the game.
+pub struct AssetCache {
+    asset_path: String,
+    entries: HashMap<String, Entry>,
+    queue: VecDeque<String>,
+}
+
+impl AssetCache {
+    pub fn new(asset_path: &str) -> Self {
+        AssetCache {
+            asset_path: asset_path.to_string(),
+            entries: HashMap::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Takes a reference to the texture at `path` within the assets, queueing it if it
+    /// isn't loaded. Returns the key to fetch and release it with.
+    pub fn load_texture(&mut self, path: &str) -> String {
+        let key = self.texture_key(path);
+        self.request(&key, Load::Texture, Vec::new()).refs += 1;
+        key
+    }
+
+    /// Takes a reference to the map and its tilesets, queueing what isn't loaded. Returns
+    /// the key to fetch and release it with.
+    pub fn load_map(&mut self, map: &MapMeta) -> String {
+        let key = self.queue_map(map);
+        self.entries.get_mut(&key).unwrap().refs += 1;
+        key
+    }
+
+    /// Releases a reference taken with `load_texture` or `load_map`.
+    pub fn release(&mut self, key: &str) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.refs = entry.refs.saturating_sub(1);
+        }
+        self.evict(key);
+    }
+
+    /// Starts loading what the player is likely to need next, replacing the previous guess.
+    pub fn preload(&mut self, textures: &[&str], maps: &[&MapMeta]) {
+        let previous: Vec<String> = self
+            .entries
+            .iter_mut()
+            .filter(|(_, entry)| entry.preload)
+            .map(|(key, entry)| {
+                entry.preload = false;
+                key.clone()
+            })
+            .collect();
+        for path in textures {
+            let key = self.texture_key(path);
+            self.request(&key, Load::Texture, Vec::new()).preload = true;
+        }
+        for map in maps {
+            let key = self.queue_map(map);
+            self.entries.get_mut(&key).unwrap().preload = true;
+        }
+        for key in previous {
+            self.evict(&key);
+        }
+    }
+
+    pub fn texture(&self, key: &str) -> Option<&Texture2D> {
+        match self.entries.get(key).map(|entry| &entry.status) {
+            Some(Status::Ready(Asset::Texture(texture))) => Some(texture),
+            _ => None,
+        }
+    }
+
+    pub fn map(&self, key: &str) -> Option<Rc<MapAssets>> {
+        match self.entries.get(key).map(|entry| &entry.status) {
+            Some(Status::Ready(Asset::Map(map))) => Some(map.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn failed(&self, key: &str) -> bool {
+        matches!(
+            self.entries.get(key).map(|entry| &entry.status),
+            Some(Status::Failed)
+        )
+    }
+
+    /// How much of `key` and what it depends on has loaded, from 0 to 1.
+    pub fn progress(&self, key: &str) -> f32 {
+        let entry = match self.entries.get(key) {
+            Some(entry) => entry,
+            None => return 0.,
+        };
+        let done = |key: &str| {
+            self.entries
+                .get(key)
+                .is_some_and(|entry| !matches!(entry.status, Status::Queued(_)))
+        };
+        let total = entry.deps.len() + 1;
+        let loaded = entry.deps.iter().filter(|dep| done(dep.as_str())).count()
+            + usize::from(done(key));
+        loaded as f32 / total as f32
+    }
+
+    /// Loads queued assets until this frame's budget is spent.
+    pub async fn process(&mut self) {
+        let start = get_time();
+        while get_time() - start < FRAME_BUDGET {
+            let key = match self.queue.pop_front() {
+                Some(key) => key,
+                None => return,
+            };
+            let load = match self.entries.get_mut(&key) {
+                Some(entry) => match std::mem::replace(&mut entry.status, Status::Failed) {
+                    Status::Queued(load) => load,
+                    status => {
+                        entry.status = status;
+                        continue;
+                    }
+                },
+                // Released before it was loaded
+                None => continue,
+            };
+            let status = match load {
+                Load::Texture => match load_texture(&key).await {
+                    Ok(texture) => Status::Ready(Asset::Texture(texture)),
+                    Err(e) => {
+                        error!("Couldn't load texture '{}': {}", key, e);
+                        Status::Failed
+                    }
+                },
+                Load::Map { loc_id, tilesets } => self.parse_map(&key, &loc_id, &tilesets).await,
+            };
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.status = status;
+            }
+        }
+    }
+
+    async fn parse_map(&self, key: &str, loc_id: &str, tilesets: &[(String, String)]) -> Status {
+        let mut textures = Vec::new();
+        for (name, tileset_key) in tilesets {
+            match self.texture(tileset_key) {
+                Some(texture) => {
+                    texture.set_filter(FilterMode::Nearest);
+                    textures.push((name.as_str(), texture.clone()));
+                }
+                None => {
+                    error!("Map '{}' is missing its tileset '{}'", key, tileset_key);
+                    return Status::Failed;
+                }
+            }
+        }
+        let json = match load_string(key).await {
+            Ok(json) => json,
+            Err(e) => {
+                error!("Couldn't load map '{}': {}", key, e);
+                return Status::Failed;
+            }
+        };
+        let tiled_map = match tiled::load_map(&json, textures.as_slice(), &[]) {
+            Ok(tiled_map) => tiled_map,
+            Err(e) => {
+                error!("Couldn't parse map '{}': {:?}", key, e);
+                return Status::Failed;
+            }
+        };
+        match MapObjects::from_tiled_json(loc_id, &json) {
+            Ok(map_objects) => Status::Ready(Asset::Map(Rc::new(MapAssets {
+                tiled_map,
+                map_objects,
+            }))),
+            Err(e) => {
+                error!("Unable to load objects of '{}': {}", key, e);
+                Status::Failed
+            }
+        }
+    }
+
+    fn texture_key(&self, path: &str) -> String {
+        let mut key = self.asset_path.clone();
+        key.push_str(path);
+        key
+    }
+
+    /// Queues the map and its tilesets, taking a reference to each tileset for the map.
+    fn queue_map(&mut self, map: &MapMeta) -> String {
+        let key = self.texture_key(&format!("maps/{}", map.tilemap_path));
+        if self.entries.contains_key(&key) {
+            return key;
+        }
+        let mut tilesets = Vec::new();
+        for (name, path) in &map.tilemap_texture_mappings {
+            let tileset_key = self.load_texture(&format!("maps/{}", path));
+            tilesets.push((name.clone(), tileset_key));
+        }
+        let deps = tilesets.iter().map(|(_, key)| key.clone()).collect();
+        let load = Load::Map {
+            loc_id: map.loc_id.clone(),
+            tilesets,
+        };
+        self.request(&key, load, deps);
+        key
+    }
+
+    fn request(&mut self, key: &str, load: Load, deps: Vec<String>) -> &mut Entry {
+        if !self.entries.contains_key(key) {
+            self.queue.push_back(key.to_string());
+        }
+        self.entries.entry(key.to_string()).or_insert(Entry {
+            status: Status::Queued(load),
+            refs: 0,
+            preload: false,
+            deps,
+        })
+    }
+
+    /// Drops `key` if nothing uses it, releasing what it depends on.
+    fn evict(&mut self, key: &str) {
+        let unused = self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.refs == 0 && !entry.preload);
+        if !unused {
+            return;
+        }
+        if let Some(entry) = self.entries.remove(key) {
+            for dep in entry.deps {
+                self.release(&dep);
+            }
+        }
+    }
+}
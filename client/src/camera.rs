@@ -0,0 +1,131 @@
+use macroquad::{
+    math::{vec2, Rect, Vec2},
+    window::{screen_height, screen_width},
+};
+
+use crate::config::Settings;
+
+/// Window height the zoom levels are designed for; other sizes scale with it.
+const REFERENCE_HEIGHT: f32 = 720.;
+
+/// Follows a target around a map, converting map pixels to screen pixels.
+pub struct FollowCamera {
+    /// Map pixel shown at the center of the screen.
+    center: Vec2,
+    map_size: Vec2,
+    /// Half of the area around the center the target can move in before the camera
+    /// follows, as a fraction of the screen.
+    dead_zone: Vec2,
+    zoom_levels: Vec<f32>,
+    zoom_index: usize,
+    pixel_perfect: bool,
+}
+
+impl FollowCamera {
+    /// A camera centered on `target` in a map of `map_size` pixels.
+    pub fn new(map_size: Vec2, target: Vec2, settings: &Settings) -> Self {
+        let mut zoom_levels = settings.zoom_levels.clone();
+        zoom_levels.retain(|zoom| *zoom > 0.);
+        zoom_levels.sort_by(f32::total_cmp);
+        if zoom_levels.is_empty() {
+            zoom_levels.push(1.);
+        }
+        let mut camera = FollowCamera {
+            center: target,
+            map_size,
+            dead_zone: vec2(0.1, 0.1),
+            zoom_levels,
+            zoom_index: 0,
+            pixel_perfect: settings.pixel_perfect,
+        };
+        let scales = camera.scales();
+        let start = camera.to_scale(settings.zoom);
+        camera.zoom_index = scales
+            .iter()
+            .position(|scale| *scale >= start)
+            .unwrap_or(scales.len() - 1);
+        camera.clamp();
+        camera
+    }
+
+    /// Screen pixels per map pixel.
+    pub fn scale(&self) -> f32 {
+        let scales = self.scales();
+        scales[self.zoom_index.min(scales.len() - 1)]
+    }
+
+    pub fn zoom_in(&mut self) {
+        let last = self.scales().len() - 1;
+        self.zoom_index = (self.zoom_index + 1).min(last);
+        self.clamp();
+    }
+
+    pub fn zoom_out(&mut self) {
+        let last = self.scales().len() - 1;
+        self.zoom_index = self.zoom_index.min(last).saturating_sub(1);
+        self.clamp();
+    }
+
+    /// The scale of each zoom level at the current window size. Pixel perfect scales are
+    /// whole numbers of at least 1, with levels that round to the same scale merged so every
+    /// zoom step changes the view.
+    fn scales(&self) -> Vec<f32> {
+        let mut scales: Vec<f32> = self
+            .zoom_levels
+            .iter()
+            .map(|zoom| self.to_scale(*zoom))
+            .collect();
+        scales.dedup();
+        scales
+    }
+
+    fn to_scale(&self, zoom: f32) -> f32 {
+        let scale = zoom * screen_height() / REFERENCE_HEIGHT;
+        if self.pixel_perfect {
+            scale.round().max(1.)
+        } else {
+            scale
+        }
+    }
+
+    /// Moves the camera just enough to keep `target` inside the dead zone.
+    pub fn follow(&mut self, target: Vec2) {
+        let dead_zone = vec2(screen_width(), screen_height()) * self.dead_zone / self.scale();
+        let offset = target - self.center;
+        self.center += offset - offset.clamp(-dead_zone, dead_zone);
+        self.clamp();
+    }
+
+    /// Keeps the view inside the map, centering maps smaller than the screen.
+    fn clamp(&mut self) {
+        let half_view = vec2(screen_width(), screen_height()) / self.scale() / 2.;
+        for axis in 0..2 {
+            self.center[axis] = if self.map_size[axis] <= half_view[axis] * 2. {
+                self.map_size[axis] / 2.
+            } else {
+                self.center[axis].clamp(half_view[axis], self.map_size[axis] - half_view[axis])
+            };
+        }
+    }
+
+    pub fn world_to_screen(&self, pos: Vec2) -> Vec2 {
+        let mut offset = vec2(screen_width(), screen_height()) / 2. - self.center * self.scale();
+        if self.pixel_perfect {
+            // Whole pixel offsets keep tiles from shimmering while scrolling
+            offset = offset.round();
+        }
+        offset + pos * self.scale()
+    }
+
+    pub fn screen_to_world(&self, pos: Vec2) -> Vec2 {
+        let origin = self.world_to_screen(Vec2::ZERO);
+        (pos - origin) / self.scale()
+    }
+
+    /// The screen rectangle covering `size` map pixels at `pos`.
+    pub fn world_rect(&self, pos: Vec2, size: Vec2) -> Rect {
+        let screen_pos = self.world_to_screen(pos);
+        let screen_size = size * self.scale();
+        Rect::new(screen_pos.x, screen_pos.y, screen_size.x, screen_size.y)
+    }
+}
@@ -1,6 +1,8 @@
 use std::fs;
 
 use anyhow::Result;
+use common::{assets::AssetSource, locale::DEFAULT_LOCALE};
+use serde::{Deserialize, Serialize};
 
 pub fn load_servers(config_dir:&str) -> Result<Vec<String>> {
     let mut config_path = String::from(config_dir);
@@ -11,7 +13,7 @@ pub fn load_servers(config_dir:&str) -> Result<Vec<String>> {
         Ok(string) => string,
         Err(_error) => {
             let servers = Vec::from([String::from("home.thesheerans.com:3333")]);
-            save_servers(&servers, &config_dir)?;
+            save_servers(&servers, config_dir)?;
             return Ok(servers);
         }
     };
@@ -25,12 +27,80 @@ pub fn save_servers(servers: &Vec<String>, config_dir:&str) -> Result<()> {
 
     let serialized = serde_json::to_string_pretty(servers)?;
     let dir_creation = fs::create_dir_all(config_dir);
-    if matches!(dir_creation, Err(_)) {
+    if dir_creation.is_err() {
         panic!("Error creating config directory!");
     }
     let file_creation = fs::write(&config_path, serialized);
-    if matches!(file_creation, Err(_)) {
+    if file_creation.is_err() {
         panic!("Error writing config file!");
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Characters of dialog revealed per second. 0 shows lines instantly.
+    pub text_speed: f32,
+    /// How many spoken lines the dialog history keeps.
+    pub history_length: usize,
+    /// Locale of the game's text, e.g. `en` or `cy`. Missing strings fall back to English.
+    pub locale: String,
+    /// Zoom levels the map camera steps through, in screen pixels per map pixel at 720p.
+    pub zoom_levels: Vec<f32>,
+    /// Zoom level the camera starts at.
+    pub zoom: f32,
+    /// Rounds the map scale to whole pixels so tiles stay crisp. Zoom levels that round to
+    /// the same scale are stepped through as one.
+    pub pixel_perfect: bool,
+    /// Lets M open the clickable campus map to travel straight to a building.
+    pub fast_travel: bool,
+    /// Repository to fetch assets from instead of the official one. `GV_ASSET_URL`,
+    /// `GV_ASSET_BRANCH` and `GV_ASSET_TAG` override it, and servers may advertise their own.
+    pub asset_source: Option<AssetSource>,
+    /// Directory to keep the assets in instead of the data directory. `GV_ASSET_PATH`
+    /// overrides it.
+    pub asset_path: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            text_speed: 40.,
+            history_length: 50,
+            locale: DEFAULT_LOCALE.to_string(),
+            zoom_levels: vec![1., 1.5, 2., 3., 4.],
+            zoom: 2.,
+            pixel_perfect: true,
+            fast_travel: true,
+            asset_source: None,
+            asset_path: None,
+        }
+    }
+}
+
+pub fn load_settings(config_dir: &str) -> Result<Settings> {
+    let mut config_path = String::from(config_dir);
+    config_path.push_str("settings.json");
+
+    let contents = match fs::read_to_string(&config_path) {
+        Ok(string) => string,
+        Err(_error) => {
+            let settings = Settings::default();
+            save_settings(&settings, config_dir)?;
+            return Ok(settings);
+        }
+    };
+    let settings: Settings = serde_json::from_str(&contents)?;
+    Ok(settings)
+}
+
+pub fn save_settings(settings: &Settings, config_dir: &str) -> Result<()> {
+    let mut config_path = String::from(config_dir);
+    config_path.push_str("settings.json");
+
+    let serialized = serde_json::to_string_pretty(settings)?;
+    fs::create_dir_all(config_dir)?;
+    fs::write(&config_path, serialized)?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use common::{map_data::Maps, quest_data::GameData};
+use tracing::{error, info};
+
+use crate::{locale::strings, map_data, quest_data, ui::markup::DialogResources};
+
+/// Maps, quests and dialog resources read from the assets.
+pub struct Content {
+    pub maps: Maps,
+    pub game_data: GameData,
+    pub dialog_resources: DialogResources,
+}
+
+/// Loads the game's content from `asset_path`, translated into the player's locale. Fails
+/// when a file the content needs is missing or broken.
+pub async fn load_content(asset_path: &str) -> Result<Content, String> {
+    info!("Loading map data...");
+    let mut maps = map_data::import_data(asset_path).await?;
+    maps.localize(strings());
+    info!("Loading quest data...");
+    let mut game_data = quest_data::import_quests(asset_path).await?;
+    for map in maps.walkable_maps() {
+        let map_objects = map_data::import_map_objects(asset_path, map).await?;
+        game_data.add_map_objects(&map_objects);
+    }
+    if let Err(e) = game_data.localize(strings()) {
+        error!("Error loading translations:\n{}", e);
+    }
+    info!("Loading dialog resources...");
+    let dialog_resources = DialogResources::load(asset_path, &game_data).await;
+    Ok(Content {
+        maps,
+        game_data,
+        dialog_resources,
+    })
+}
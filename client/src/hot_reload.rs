@@ -0,0 +1,97 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use common::lint::{lint_dir, Severity};
+use tracing::{error, warn};
+
+/// How often the assets are checked for changes.
+const SCAN_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches the local assets in developer mode so edited content can be reloaded without
+/// restarting the game.
+pub struct ContentWatcher {
+    root: PathBuf,
+    /// When each file was last modified, as of the last scan.
+    stamps: HashMap<PathBuf, SystemTime>,
+    next_scan: Instant,
+    changed: bool,
+}
+
+impl ContentWatcher {
+    pub fn new(asset_path: &str) -> Self {
+        let root = PathBuf::from(asset_path);
+        ContentWatcher {
+            stamps: scan(&root),
+            root,
+            next_scan: Instant::now() + SCAN_INTERVAL,
+            changed: false,
+        }
+    }
+
+    /// Whether the assets changed since the last reload. Only reports a change once a scan
+    /// finds nothing new, so an editor saving several files causes a single reload.
+    pub fn poll(&mut self) -> bool {
+        let now = Instant::now();
+        if now < self.next_scan {
+            return false;
+        }
+        self.next_scan = now + SCAN_INTERVAL;
+        let stamps = scan(&self.root);
+        if stamps != self.stamps {
+            self.stamps = stamps;
+            self.changed = true;
+            return false;
+        }
+        std::mem::take(&mut self.changed)
+    }
+}
+
+/// Modification times of the files under `dir`. Hidden files and directories, like the git
+/// repository, are left out.
+fn scan(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    let mut stamps = HashMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                dirs.push(entry.path());
+            } else if let Ok(modified) = meta.modified() {
+                stamps.insert(entry.path(), modified);
+            }
+        }
+    }
+    stamps
+}
+
+/// Checks the content at `asset_path` before it's reloaded, logging what's wrong with it.
+/// Returns `false` if loading it would crash the game.
+pub fn can_reload(asset_path: &str) -> bool {
+    let issues = match lint_dir(Path::new(asset_path)) {
+        Ok(issues) => issues,
+        Err(e) => {
+            error!("Not reloading the content: {}", e);
+            return false;
+        }
+    };
+    for issue in &issues {
+        warn!("{}", issue);
+    }
+    if issues.iter().any(|issue| issue.severity == Severity::Fatal) {
+        error!("Not reloading the content until the fatal problems are fixed");
+        return false;
+    }
+    true
+}
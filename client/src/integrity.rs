@@ -0,0 +1,248 @@
+use std::{fmt, fs, path::Path};
+
+use common::{
+    assets::{referenced_files, sha256_hex, AssetKind, AssetSource, CONTENT_FILES, OPTIONAL_FILES},
+    map_data::Maps,
+    quest_data::GameData,
+};
+use git2::{build::CheckoutBuilder, ObjectType, Oid, Repository};
+use tracing::{info, warn};
+
+use crate::{
+    asset_updater::{local_manifest, update, UpdateError, UpdateProgress},
+    scenes::outside::CAMPUS_PICTURE,
+};
+
+/// How many times `repair_all` repairs before giving up.
+const REPAIR_ROUNDS: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProblemKind {
+    Missing,
+    /// The file differs from the one the assets were updated to.
+    Modified,
+    /// The file is there but can't be read as content.
+    Unreadable(String),
+}
+
+/// What a repair is allowed to do to the assets.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RepairOptions {
+    /// Only restore files from the local repository, never fetching them again.
+    pub offline: bool,
+    /// Leave the files an author changed alone, as in developer mode.
+    pub keep_modified: bool,
+}
+
+impl RepairOptions {
+    /// The problems with the assets at `assets_path` a repair should fix.
+    pub fn problems(&self, assets_path: &str) -> Vec<AssetProblem> {
+        let mut problems = verify_assets(assets_path);
+        if self.keep_modified {
+            problems.retain(|problem| problem.kind != ProblemKind::Modified);
+        }
+        problems
+    }
+}
+
+/// A file the content needs that isn't as it should be.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetProblem {
+    /// Path relative to the assets.
+    pub path: String,
+    pub kind: ProblemKind,
+}
+
+impl fmt::Display for AssetProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ProblemKind::Missing => write!(f, "'{}' is missing", self.path),
+            ProblemKind::Modified => write!(f, "'{}' has been changed", self.path),
+            ProblemKind::Unreadable(e) => write!(f, "'{}' can't be read: {}", self.path, e),
+        }
+    }
+}
+
+/// How files should look: the blobs of the checked out commit, or the hashes in the manifest
+/// of the last pack downloaded.
+enum Record {
+    Git(Repository),
+    Pack(Vec<(String, String)>),
+    None,
+}
+
+impl Record {
+    fn open(assets_path: &str) -> Self {
+        if let Ok(repo) = Repository::open(assets_path) {
+            return Record::Git(repo);
+        }
+        match local_manifest(assets_path) {
+            Some(manifest) => Record::Pack(
+                manifest
+                    .files
+                    .into_iter()
+                    .map(|entry| (entry.path, entry.sha256))
+                    .collect(),
+            ),
+            None => Record::None,
+        }
+    }
+
+    fn records(&self, path: &str) -> bool {
+        match self {
+            Record::Git(repo) => repo
+                .head()
+                .and_then(|head| head.peel_to_tree())
+                .is_ok_and(|tree| tree.get_path(Path::new(path)).is_ok()),
+            Record::Pack(files) => files.iter().any(|(file, _)| file == path),
+            Record::None => false,
+        }
+    }
+
+    /// Whether `data` is what's recorded for `path`, or `None` when nothing is recorded.
+    fn matches(&self, path: &str, data: &[u8]) -> Option<bool> {
+        match self {
+            Record::Git(repo) => {
+                let tree = repo.head().ok()?.peel_to_tree().ok()?;
+                let expected = tree.get_path(Path::new(path)).ok()?.id();
+                Some(Oid::hash_object(ObjectType::Blob, data).ok()? == expected)
+            }
+            Record::Pack(files) => {
+                let (_, hash) = files.iter().find(|(file, _)| file == path)?;
+                Some(sha256_hex(data) == *hash)
+            }
+            Record::None => None,
+        }
+    }
+}
+
+/// Checks every file the content refers to exists and matches the version the assets were
+/// updated to. Files nothing is recorded for, like local additions, only have to exist.
+pub fn verify_assets(assets_path: &str) -> Vec<AssetProblem> {
+    let problems = find_problems(assets_path);
+    for problem in &problems {
+        warn!("Asset problem: {}", problem);
+    }
+    problems
+}
+
+fn find_problems(assets_path: &str) -> Vec<AssetProblem> {
+    let record = Record::open(assets_path);
+    let root = Path::new(assets_path);
+
+    // The content files say which other files are needed, so they're checked first
+    let mut problems: Vec<AssetProblem> = CONTENT_FILES
+        .iter()
+        .chain(OPTIONAL_FILES.iter())
+        .filter_map(|file| check_file(root, &record, file))
+        .collect();
+    if !problems.is_empty() {
+        return problems;
+    }
+    let (maps, game_data) = match read_content(root) {
+        Ok(content) => content,
+        Err(problem) => return vec![problem],
+    };
+    let mut files = referenced_files(&maps, &game_data);
+    files.push(CAMPUS_PICTURE.to_string());
+    for file in files {
+        let is_content =
+            CONTENT_FILES.contains(&file.as_str()) || OPTIONAL_FILES.contains(&file.as_str());
+        if !is_content {
+            problems.extend(check_file(root, &record, &file));
+        }
+    }
+    problems
+}
+
+fn check_file(root: &Path, record: &Record, file: &str) -> Option<AssetProblem> {
+    let kind = match fs::read(root.join(file)) {
+        Ok(data) => match record.matches(file, &data) {
+            Some(false) => ProblemKind::Modified,
+            _ => return None,
+        },
+        // Optional files only have to be there if the assets have them
+        Err(_) if OPTIONAL_FILES.contains(&file) && !record.records(file) => return None,
+        Err(_) => ProblemKind::Missing,
+    };
+    Some(AssetProblem {
+        path: file.to_string(),
+        kind,
+    })
+}
+
+fn read_content(root: &Path) -> Result<(Maps, GameData), AssetProblem> {
+    let unreadable = |path: &str, e: String| AssetProblem {
+        path: path.to_string(),
+        kind: ProblemKind::Unreadable(e),
+    };
+    let map_data = fs::read_to_string(root.join("map_data.json"))
+        .map_err(|e| unreadable("map_data.json", e.to_string()))?;
+    let maps =
+        Maps::from_json(&map_data).map_err(|e| unreadable("map_data.json", e.to_string()))?;
+    let questlines = fs::read_to_string(root.join("questlines.json"))
+        .map_err(|e| unreadable("questlines.json", e.to_string()))?;
+    let objects =
+        fs::read_to_string(root.join("objects.json")).unwrap_or_else(|_| String::from("[]"));
+    let game_data = GameData::from_json(&questlines, &objects)
+        .map_err(|e| unreadable("questlines.json", e.to_string()))?;
+    Ok((maps, game_data))
+}
+
+/// Repairs the assets until they verify, since fixing the content files can reveal more
+/// files they need.
+pub fn repair_all(
+    assets_path: &str,
+    source: &AssetSource,
+    options: RepairOptions,
+    progress: &UpdateProgress,
+) -> Result<(), UpdateError> {
+    for _ in 0..REPAIR_ROUNDS {
+        let problems = options.problems(assets_path);
+        if problems.is_empty() {
+            return Ok(());
+        }
+        repair_assets(assets_path, source, &problems, options, progress)?;
+    }
+    match options.problems(assets_path).len() {
+        0 => Ok(()),
+        remaining => Err(UpdateError::Unrepaired(remaining)),
+    }
+}
+
+/// Restores the files in `problems`: from the local repository for git assets, falling back
+/// to fetching them again unless offline, or by downloading them again for packs.
+pub fn repair_assets(
+    assets_path: &str,
+    source: &AssetSource,
+    problems: &[AssetProblem],
+    options: RepairOptions,
+    progress: &UpdateProgress,
+) -> Result<(), UpdateError> {
+    info!("Repairing {} asset files", problems.len());
+    if source.kind == AssetKind::Git {
+        match checkout_files(assets_path, problems) {
+            Ok(()) => return Ok(()),
+            Err(e) if options.offline => return Err(UpdateError::Git(e)),
+            Err(e) => warn!(
+                "Couldn't restore assets locally, fetching them again: {}",
+                e
+            ),
+        }
+    }
+    if options.offline {
+        return Err(UpdateError::Offline);
+    }
+    // Updating only downloads the files that don't match
+    update(assets_path, source, progress)
+}
+
+fn checkout_files(assets_path: &str, problems: &[AssetProblem]) -> Result<(), git2::Error> {
+    let repo = Repository::open(assets_path)?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force();
+    for problem in problems {
+        checkout.path(problem.path.as_str());
+    }
+    repo.checkout_head(Some(&mut checkout))
+}
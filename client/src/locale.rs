@@ -0,0 +1,57 @@
+use std::sync::OnceLock;
+
+use common::locale::{parse_table, Localization, StringTable, DEFAULT_LOCALE};
+use macroquad::file::load_string;
+use tracing::{error, warn};
+
+static STRINGS: OnceLock<Localization> = OnceLock::new();
+
+/// Loads `<assets>/locales/<locale>.json` along with the English table it falls back to.
+pub async fn load_localization(asset_path: &str, locale: &str) -> Localization {
+    let english = load_table(asset_path, DEFAULT_LOCALE).await;
+    let strings = if locale == DEFAULT_LOCALE {
+        StringTable::new()
+    } else {
+        load_table(asset_path, locale).await
+    };
+    Localization::new(locale, strings, english)
+}
+
+async fn load_table(asset_path: &str, locale: &str) -> StringTable {
+    let mut table_path = String::from(asset_path);
+    table_path.push_str("locales/");
+    table_path.push_str(locale);
+    table_path.push_str(".json");
+    match load_string(&table_path).await {
+        Ok(json) => parse_table(&json).unwrap_or_else(|e| {
+            error!("Couldn't parse '{}': {}", table_path, e);
+            StringTable::new()
+        }),
+        Err(_) => {
+            if locale != DEFAULT_LOCALE {
+                warn!("No strings for locale '{}'", locale);
+            }
+            StringTable::new()
+        }
+    }
+}
+
+/// Sets the strings used by `tr`. Only the first call has an effect.
+pub fn set_localization(strings: Localization) {
+    let _ = STRINGS.set(strings);
+}
+
+/// The player's strings, or built in English before they're loaded.
+pub fn strings() -> &'static Localization {
+    STRINGS.get_or_init(Localization::english)
+}
+
+/// The UI string for `key` in the player's locale.
+pub fn tr(key: &'static str) -> &'static str {
+    strings().get(key)
+}
+
+/// The UI string for `key` with `{0}`, `{1}`, ... replaced by `args`.
+pub fn tr_format(key: &str, args: &[&str]) -> String {
+    strings().format(key, args)
+}
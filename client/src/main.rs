@@ -1,20 +1,21 @@
 pub mod asset_updater;
+pub mod assets;
+pub mod camera;
 pub mod config;
+pub mod content;
+pub mod hot_reload;
+pub mod integrity;
+pub mod locale;
 pub mod map_data;
+pub mod pack_updater;
 pub mod quest_data;
 pub mod scenes;
 pub mod ui;
 
-use std::{
-    net::{TcpStream, ToSocketAddrs}, sync::{Arc, Mutex}, time::Duration
-};
+use std::env;
 
-use common::{
-    conn_lib::{read_stream_client, write_flush_client},
-    ClientState,
-};
-use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
-use tracing::{error, info};
+use common::assets::AssetSource;
+use tracing::{error, info, warn};
 
 use macroquad::{
     prelude::*,
@@ -24,14 +25,30 @@ use macroquad::{
 use directories::BaseDirs;
 
 use crate::{
-    config::{load_servers, save_servers},
+    asset_updater::{has_local_assets, AssetUpdate, UpdateError, ASSET_PATH_VAR},
+    assets::AssetCache,
+    config::{load_servers, load_settings},
+    content::load_content,
+    hot_reload::ContentWatcher,
+    integrity::{repair_all, ProblemKind, RepairOptions},
+    locale::{load_localization, set_localization, tr},
     scenes::{
-        inside::render_inside, login::render_login, message_popup::show_popup,
-        outside::render_outside, server_select::run_server_selector,
+        editor::EditorScene,
+        message_popup::{show_update_progress, MessageScene, TIMEOUT},
+        server_select::ServerSelectScene,
+        GameContext, Scene, SceneManager,
     },
     ui::theme::generate_theme,
 };
 
+/// Command line flag that starts the game from the local assets without fetching them.
+const OFFLINE_FLAG: &str = "--offline";
+/// Command line flag for content authors: runs offline, reloads the content whenever the
+/// local assets change and outlines the map exits.
+const DEV_FLAG: &str = "--dev";
+/// Command line flag that opens the quest editor on the local assets instead of the game.
+const EDITOR_FLAG: &str = "--editor";
+
 #[allow(dead_code)]
 fn window_conf() -> Conf {
     Conf {
@@ -49,8 +66,6 @@ fn window_conf() -> Conf {
     }
 }
 
-static TIMEOUT: f64 = 3.;
-
 #[macroquad::main("Gwynedd Valley")]
 async fn main() {
     // Setup Logging
@@ -66,237 +81,196 @@ async fn main() {
         config_path.push_str("gwynedd-valley/");
     }
 
-    let mut asset_path = String::from("");
-    if let Some(base_dirs) = BaseDirs::new() {
-        asset_path = base_dirs.data_dir().to_str().unwrap().to_string();
-        asset_path.push_str("/gwynedd-valley/");
-    } else {
-        asset_path.push_str("gwynedd-valley/");
+    info!("Loading config from path: {}", &config_path);
+    info!("Loading settings...");
+    let settings = load_settings(&config_path).unwrap_or_else(|e| {
+        error!("Error loading settings, using defaults:\n{}", e);
+        Default::default()
+    });
+
+    // Environment variables win over the settings, which win over the data directory
+    let mut asset_path = match env::var(ASSET_PATH_VAR).ok().or(settings.asset_path.clone()) {
+        Some(path) => path,
+        None => match BaseDirs::new() {
+            Some(base_dirs) => {
+                let mut path = base_dirs.data_dir().to_str().unwrap().to_string();
+                path.push_str("/gwynedd-valley/");
+                path
+            }
+            None => String::from("gwynedd-valley/"),
+        },
+    };
+    if !asset_path.ends_with('/') {
+        asset_path.push('/');
     }
+    let configured_source = settings.asset_source.clone().unwrap_or_default();
+    let env_source = AssetSource::from_env(&configured_source);
+    let asset_source_locked = env_source.is_some();
+    let asset_source = env_source.unwrap_or(configured_source);
 
     // Setup Theming
-    let default_theme = Arc::new(root_ui().default_skin().clone());
-    let custom_theme = Arc::new(generate_theme());
+    let default_theme = root_ui().default_skin().clone();
+    let custom_theme = generate_theme();
 
-    // Update Assets
-    info!("Updating assets at path: {}", &asset_path);
-    match asset_updater::update_assets(&asset_path) {
-        Ok(_) => (),
-        Err(e) => panic!("Error updating assets: {}", e),
+    // Update Assets, falling back to the local copy when they can't be fetched
+    let dev = env::args().any(|arg| arg == DEV_FLAG);
+    let editor = env::args().any(|arg| arg == EDITOR_FLAG);
+    let offline = dev || editor || env::args().any(|arg| arg == OFFLINE_FLAG);
+    let mut update_failed = false;
+    let mut update_cancelled = false;
+    if offline {
+        info!("Running offline, using the assets at path {}", &asset_path);
+    } else {
+        info!(
+            "Updating assets at path {} from {} ({})",
+            &asset_path,
+            &asset_source.url,
+            asset_source.reference()
+        );
+        // The strings come with the assets, so the update screen can't be translated
+        let result = show_update_progress(
+            &custom_theme,
+            AssetUpdate::start(&asset_path, &asset_source),
+            "Updating assets...",
+            "Cancel",
+        )
+        .await;
+        if let Err(e) = result {
+            error!("Error updating assets: {}", e);
+            update_failed = true;
+            update_cancelled = matches!(e, UpdateError::Cancelled);
+        }
+    }
+
+    // Check the files the content needs before loading it
+    let repair_options = RepairOptions {
+        offline,
+        // Authors edit the assets, so only missing and unreadable files are problems
+        keep_modified: dev || editor,
+    };
+    let mut problems = repair_options.problems(&asset_path);
+    if !problems.is_empty() && update_failed && !update_cancelled {
+        // The update didn't put them back, so restore what can be from the local copy
+        repair(&custom_theme, &asset_path, &asset_source, repair_options).await;
+        problems = repair_options.problems(&asset_path);
+    }
+    let unreadable = problems
+        .iter()
+        .any(|problem| matches!(problem.kind, ProblemKind::Unreadable(_)));
+    if !has_local_assets(&asset_path) || unreadable {
+        error!("No usable assets at path {}", &asset_path);
+        show_fatal_error(if unreadable {
+            "The game's assets are broken. Start the game online to repair them."
+        } else if offline {
+            "No assets found. Start the game without --offline to download them."
+        } else if update_cancelled {
+            "The asset download was cancelled. Start the game again to resume it."
+        } else {
+            "Couldn't download the game's assets. Check your connection and try again."
+        })
+        .await;
+        return;
+    }
+    if update_failed {
+        warn!("Using the existing assets at path {}", &asset_path);
     }
-    info!("Loading config from path: {}", &config_path);
 
     // Load Saved Servers
     info!("Loading saved servers...");
-    let mut servers = load_servers(&config_path).unwrap_or_default();
-    info!("Loading map data...");
-    let map_data = map_data::import_data(&asset_path).await;
-    info!("Loading quest data...");
-    let game_data = quest_data::import_quests(&asset_path).await;
-
-    let mut net_socket;
-    // let mut net_key: SymKey;
-
-    let mut state: ClientState;
-
-    'server_select: loop {
-        // Show Server Selection Screen
-        info!("Displaying server selector...");
-        let server_count = servers.len();
-        let server = run_server_selector(custom_theme.clone(), &mut servers).await;
-        if servers.len() != server_count {
-            let e = save_servers(&servers, &config_path);
-            if e.is_err() {
-                error!("Error saving server config:\n{}", e.unwrap_err());
-            }
-        }
-        #[allow(unused_labels)]
-        'server_connect: loop {
-            info!("Connecting to: {}", &server);
-
-            // Show connecting screen
-            let mut counter = 0;
-            loop {
-                clear_background(GRAY);
-                show_popup(&custom_theme, String::from("Connecting..."));
-                if counter < 3 {
-                    counter += 1;
-                } else {
-                    break;
-                }
-                next_frame().await
-            }
-
-            // Connect to Server
-            info!("Creating server connection...");
-            let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
-            connector.set_verify(SslVerifyMode::NONE);
-            let connector = connector.build();
-            let stream =
-                match TcpStream::connect((&server).to_socket_addrs().unwrap().next().unwrap()) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        error!("{}", e);
-                        err_msg(&custom_theme, "Couldn't Connect to Server").await;
-                        continue 'server_select;
-                    }
-                };
-                let _ = stream.set_read_timeout(Some(Duration::from_millis(250)));
-                let _ = stream.set_write_timeout(Some(Duration::from_millis(250)));
-            net_socket = Arc::new(Mutex::new(
-                connector.connect("home.thesheerans.com", stream).unwrap(),
-            ));
-            info!("Connected to server.");
-
-            info!("Negotiating secure connection...");
-            // let connect_res = conn_lib::establish_connection_client(&mut net_socket);
-
-            // if connect_res.is_err() {
-            //     err_msg(&custom_theme, "!!!COULD NOT CONNECT TO SERVER!!!").await;
-            //     continue 'server_select;
-            // }
-            // net_key = connect_res.unwrap();
-
-            info!("Done.");
-            break;
-        }
-
-        let auth = render_login(&custom_theme).await;
-        // Show loading screen
-        let mut counter = 0;
-        loop {
-            clear_background(GRAY);
-            show_popup(&custom_theme, String::from("Loading..."));
-            if counter < 3 {
-                counter += 1;
+    let servers = load_servers(&config_path).unwrap_or_default();
+    info!("Loading strings for locale '{}'...", &settings.locale);
+    set_localization(load_localization(&asset_path, &settings.locale).await);
+    let mut content = load_content(&asset_path).await;
+    if let Err(e) = &content {
+        error!("Error loading content: {}", e);
+        repair(&custom_theme, &asset_path, &asset_source, repair_options).await;
+        content = load_content(&asset_path).await;
+    }
+    let content = match content {
+        Ok(content) => content,
+        Err(e) => {
+            error!("Error loading content: {}", e);
+            show_fatal_error(if offline {
+                "The game's content is broken. Start the game without --offline to repair it."
             } else {
-                break;
-            }
-            next_frame().await
-        }
-
-        info!("Logging in as '{}'", auth.username);
-        let auth_ser = serde_json::to_string(&auth).expect("Failed to serialize the auth packet");
-        drop(auth);
-        let auth_send_status = write_flush_client(net_socket.clone(), auth_ser);
-        // let auth_send_status = conn_lib_2::send_msg_client(auth_ser, &mut net_socket, &net_key);
-        // let auth_send_status = net_client.writer().write_all(auth_ser.as_bytes());
-        // let _ = net_client.writer().flush();
-        // let _ = net_client.write_tls(&mut net_socket);
-        if auth_send_status.is_err() {
-            error!("Couldn't send auth packet");
-            let timer = get_time();
-            loop {
-                if get_time() - timer > TIMEOUT {
-                    continue 'server_select;
-                }
-                clear_background(RED);
-                show_popup(
-                    &custom_theme,
-                    String::from("!!!Couldn't send auth packet!!!"),
-                );
-                next_frame().await
-            }
-        }
-
-        info!("Getting Client State");
-        // Read Server Response to Auth
-        // let _ = net_client.read_tls(&mut net_socket);
-        // let _ = net_client.process_new_packets();
-        // let server_msg = net_client.reader().read_to_string(&mut msg);
-        let server_msg = read_stream_client(net_socket.clone()); //conn_lib_2::read_msg_client(&mut net_socket, &net_key);
-
-        if server_msg.is_err() {
-            error!("{}", server_msg.unwrap_err());
-            err_msg(&custom_theme, "Server connection closed").await;
-            continue 'server_select;
-        }
-        let msg = server_msg.unwrap();
-        let state2;
-
-        let state_temp = serde_json::from_str::<ClientState>(&msg);
-        if state_temp.is_ok() {
-            state2 = Some(state_temp.unwrap());
-        } else {
-            error!("Error parsing server message: {}", state_temp.unwrap_err());
-            let _ = net_socket.lock().unwrap().shutdown();
-            continue 'server_select;
+                "The game's content is broken and couldn't be repaired."
+            })
+            .await;
+            return;
         }
+    };
+    let watcher = dev.then(|| {
+        info!("Developer mode, watching the assets at path {}", &asset_path);
+        ContentWatcher::new(&asset_path)
+    });
 
-        if state2.is_none() {
-            let timer = get_time();
-            loop {
-                if get_time() - timer > TIMEOUT {
-                    continue 'server_select;
-                }
-                clear_background(RED);
-                show_popup(
-                    &custom_theme,
-                    String::from("!!!Server did not send state!!!"),
-                );
-                next_frame().await
-            }
-        }
-        state = state2.unwrap();
-        if !state.authenticated {
-            let _ = net_socket.lock().unwrap().shutdown();
-            let timer = get_time();
-            loop {
-                if get_time() - timer > TIMEOUT {
-                    continue 'server_select;
-                }
-                clear_background(RED);
-                show_popup(&custom_theme, String::from("Authentication Error"));
-                next_frame().await
-            }
-        }
-        info!("State Received");
-        break;
+    let mut ctx = GameContext {
+        config_path,
+        assets: AssetCache::new(&asset_path),
+        asset_path,
+        offline,
+        watcher,
+        asset_source,
+        asset_source_locked,
+        theme: custom_theme,
+        default_theme,
+        servers,
+        settings,
+        maps: content.maps,
+        game_data: content.game_data,
+        dialog_resources: content.dialog_resources,
+        session: None,
+    };
+    let mut first: Box<dyn Scene> = if editor {
+        info!("Opening the quest editor...");
+        Box::<EditorScene>::default()
+    } else {
+        info!("Displaying server selector...");
+        Box::new(ServerSelectScene::new())
+    };
+    if !problems.is_empty() {
+        first = Box::new(MessageScene::new(tr("warning.broken_assets"), ORANGE, Some(first)));
     }
+    if update_failed {
+        first = Box::new(MessageScene::new(tr("warning.offline_assets"), ORANGE, Some(first)));
+    }
+    SceneManager::default().run(&mut ctx, first).await;
+}
 
-    loop {
-        info!("Current Location: {}", &state.location);
-        if state.location.eq_ignore_ascii_case("outside") {
-            info!("Rendering outside...");
-            let loc = render_outside(
-                &custom_theme,
-                &asset_path,
-                &map_data.outside,
-                &game_data,
-                &mut state,
-                net_socket.clone()
-            )
-            .await;
-            if loc == "exit" {
-                err_msg(&custom_theme, "Closing...").await;
-                break;
-            }
-            state.location = loc;
-            info!("Going to {}", &state.location);
-        }
-        info!("Rendering inside({})...", &state.location);
-        render_inside(
-            &custom_theme,
-            &default_theme,
-            &asset_path,
-            &map_data.insides,
-            &game_data,
-            &mut state,
-            // net_socket.clone()
-        )
-        .await;
-        info!("{:#?}", &state);
+/// Restores the broken assets before any scene is shown, so the update screen can't be
+/// translated.
+async fn repair(
+    theme: &Skin,
+    asset_path: &str,
+    asset_source: &AssetSource,
+    options: RepairOptions,
+) {
+    let repair = {
+        let asset_path = asset_path.to_owned();
+        let asset_source = asset_source.clone();
+        AssetUpdate::spawn(move |progress| {
+            repair_all(&asset_path, &asset_source, options, progress)
+        })
+    };
+    let result = show_update_progress(theme, repair, "Repairing assets...", "Cancel").await;
+    if let Err(e) = result {
+        error!("Error repairing assets: {}", e);
     }
 }
 
-async fn err_msg(custom_theme: &Skin, msg: &str) {
-    let timer = get_time();
-    loop {
-        if get_time() - timer > TIMEOUT {
-            break;
-        }
+/// Shows an error for a few seconds when the game can't start.
+async fn show_fatal_error(msg: &str) {
+    let start = get_time();
+    while get_time() - start < TIMEOUT {
         clear_background(RED);
-        show_popup(&custom_theme, String::from(msg));
+        let size = measure_text(msg, None, 30, 1.);
+        draw_text(
+            msg,
+            screen_width() / 2. - size.width / 2.,
+            screen_height() / 2.,
+            30.,
+            WHITE,
+        );
         next_frame().await
     }
 }
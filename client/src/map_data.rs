@@ -1,32 +1,26 @@
-use glam::f32::Vec2;
+use common::{
+    map_data::{MapMeta, Maps},
+    map_objects::MapObjects,
+};
 use macroquad::file::load_string;
-use serde::Deserialize;
 
-#[derive(Deserialize, Clone, Debug)]
-pub struct Maps {
-    pub outside: Vec<MapLocation>,
-    pub insides: Vec<MapMeta>,
-}
-
-#[derive(Deserialize, Clone, Debug)]
-pub struct MapLocation {
-    pub loc_id: String,
-    pub tl_corner: Vec2,
-    pub br_corner: Vec2,
-    pub label: String,
+pub async fn import_data(asset_path: &str) -> Result<Maps, String> {
+    let mut data_path = String::from(asset_path);
+    data_path.push_str("map_data.json");
+    let map_data = load_string(&data_path)
+        .await
+        .map_err(|e| format!("Unable to read '{}': {}", data_path, e))?;
+    Maps::from_json(&map_data).map_err(|e| format!("Unable to deserialize map data: {}", e))
 }
 
-#[derive(Deserialize, Clone, Debug)]
-pub struct MapMeta {
-    pub loc_id: String,
-    pub tilemap_path: String,
-    pub tilemap_texture_mappings: Vec<(String, String)>,
-    pub spawn_location: Vec2
+/// Reads the layers and objects of an interior's Tiled map.
+pub async fn import_map_objects(asset_path: &str, map: &MapMeta) -> Result<MapObjects, String> {
+    let mut map_path = String::from(asset_path);
+    map_path.push_str("maps/");
+    map_path.push_str(&map.tilemap_path);
+    let tiled_map = load_string(&map_path)
+        .await
+        .map_err(|e| format!("Unable to read '{}': {}", map_path, e))?;
+    MapObjects::from_tiled_json(&map.loc_id, &tiled_map)
+        .map_err(|e| format!("Unable to load objects of '{}': {}", map_path, e))
 }
-
-pub async fn import_data(asset_path: &str) -> Maps {
-    let mut data_path = String::from(asset_path);
-    data_path.push_str("map_data.json");
-    let map_data = load_string(&data_path).await.unwrap();
-    serde_json::from_str::<Maps>(&map_data).expect("Unable to deserialize map data")
-}
\ No newline at end of file
@@ -0,0 +1,105 @@
+use std::{fs, io::Read, path::Path};
+
+use common::assets::{
+    encode_url_path, is_safe_path, sha256_hex, AssetManifest, AssetSource, ManifestEntry,
+    MANIFEST_FILE, SIGNATURE_FILE,
+};
+use tracing::info;
+
+use crate::asset_updater::{local_manifest, UpdateError, UpdateProgress};
+
+/// Public key (PEM, Ed25519) asset packs have to be signed with, set when building the client.
+const PACK_PUBLIC_KEY: Option<&str> = option_env!("GV_PACK_PUBLIC_KEY");
+
+/// Brings the assets at `assets_path` up to date with the pack at `source.url`, only
+/// downloading the files that are missing or changed.
+///
+/// Files already downloaded are checked against the manifest rather than trusted, so an
+/// update that's cancelled or cut off picks up where it stopped.
+pub fn update_pack(
+    assets_path: &str,
+    source: &AssetSource,
+    progress: &UpdateProgress,
+) -> Result<(), UpdateError> {
+    let public_key = PACK_PUBLIC_KEY.ok_or(UpdateError::NoPublicKey)?;
+    let base = source.url.trim_end_matches('/');
+
+    // Only trust the manifest once its signature checks out
+    let manifest_data = download(&format!("{}/{}", base, MANIFEST_FILE))?;
+    let signature = download(&format!("{}/{}", base, SIGNATURE_FILE))?;
+    let manifest = AssetManifest::verify(&manifest_data, &signature, public_key.as_bytes())?;
+    if let Some(commit) = &source.commit {
+        if !manifest
+            .version
+            .as_ref()
+            .is_some_and(|version| version.starts_with(commit.as_str()))
+        {
+            return Err(UpdateError::WrongVersion(commit.clone()));
+        }
+    }
+
+    let root = Path::new(assets_path);
+    fs::create_dir_all(root)?;
+    let stale: Vec<&ManifestEntry> = manifest
+        .files
+        .iter()
+        .filter(|entry| !is_current(root, entry))
+        .collect();
+    info!(
+        "Downloading {} of {} asset files from {}",
+        stale.len(),
+        manifest.files.len(),
+        source.url
+    );
+    for (i, entry) in stale.iter().enumerate() {
+        progress.report(i, stale.len());
+        if progress.is_cancelled() {
+            return Err(UpdateError::Cancelled);
+        }
+        let data = download(&format!("{}/{}", base, encode_url_path(&entry.path)))?;
+        if sha256_hex(&data) != entry.sha256 {
+            return Err(UpdateError::Corrupt(entry.path.clone()));
+        }
+        // Write beside the file and swap it in, so a cut off download never leaves half a file
+        let path = root.join(&entry.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let part = root.join(format!("{}.part", entry.path));
+        fs::write(&part, &data)?;
+        fs::rename(&part, &path)?;
+    }
+    progress.report(stale.len(), stale.len());
+
+    // Remove the files the previous pack had that this one dropped
+    if let Some(old) = local_manifest(assets_path) {
+        for entry in old.files {
+            let dropped = !manifest.files.iter().any(|new| new.path == entry.path);
+            if dropped && is_safe_path(&entry.path) {
+                let _ = fs::remove_file(root.join(&entry.path));
+            }
+        }
+    }
+
+    // Keep the manifest to know what was downloaded next time
+    fs::write(root.join(MANIFEST_FILE), &manifest_data)?;
+    fs::write(root.join(SIGNATURE_FILE), &signature)?;
+    info!("Assets updated from the pack at {}", source.url);
+    Ok(())
+}
+
+/// Whether the file for `entry` is already downloaded and intact.
+fn is_current(root: &Path, entry: &ManifestEntry) -> bool {
+    let path = root.join(&entry.path);
+    let size_matches = fs::metadata(&path).is_ok_and(|meta| meta.len() == entry.size);
+    size_matches && fs::read(&path).is_ok_and(|data| sha256_hex(&data) == entry.sha256)
+}
+
+fn download(url: &str) -> Result<Vec<u8>, UpdateError> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| UpdateError::Download(format!("{}: {}", url, e)))?;
+    let mut data = Vec::new();
+    response.into_reader().read_to_end(&mut data)?;
+    Ok(data)
+}
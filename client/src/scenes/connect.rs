@@ -0,0 +1,206 @@
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{
+    assets::{AssetSource, SERVER_HOST},
+    conn_lib::read_stream_client,
+    ServerHello,
+};
+use macroquad::prelude::*;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+
+use crate::{
+    asset_updater::{checked_out_commit, is_compatible, AssetUpdate, UpdateError},
+    locale::tr,
+    scenes::{
+        login::{LoginScene, Stream},
+        message_popup::{show_popup, show_update_progress, MessageScene},
+        server_select::ServerSelectScene,
+        GameContext, Scene, SceneFuture, Transition,
+    },
+};
+
+/// Shows the connecting screen for a few frames, then connects to `server`.
+pub struct ConnectScene {
+    server: String,
+    counter: u32,
+}
+
+impl ConnectScene {
+    pub fn new(server: String) -> Self {
+        ConnectScene { server, counter: 0 }
+    }
+}
+
+impl Scene for ConnectScene {
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        show_popup(&ctx.theme, String::from(tr("status.connecting")));
+        // Let the connecting screen show before blocking on the connection
+        if self.counter < 3 {
+            self.counter += 1;
+            return Transition::None;
+        }
+
+        // Connect to Server
+        info!("Connecting to: {}", &self.server);
+        let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        let connector = connector.build();
+        let addr = match self.server.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(addr)) => addr,
+            Ok(None) => {
+                error!("No address found for '{}'", &self.server);
+                return Transition::Replace(Box::new(MessageScene::error(tr("error.connect"))));
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Transition::Replace(Box::new(MessageScene::error(tr("error.connect"))));
+            }
+        };
+        let stream = match TcpStream::connect(addr) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("{}", e);
+                return Transition::Replace(Box::new(MessageScene::error(tr("error.connect"))));
+            }
+        };
+        let _ = stream.set_read_timeout(Some(Duration::from_millis(250)));
+        let _ = stream.set_write_timeout(Some(Duration::from_millis(250)));
+        let stream = Arc::new(Mutex::new(
+            connector.connect("home.thesheerans.com", stream).unwrap(),
+        ));
+        info!("Connected to server.");
+
+        // Servers may advertise the assets their content is built from
+        let hello = read_stream_client(stream.clone())
+            .ok()
+            .and_then(|msg| serde_json::from_str::<ServerHello>(&msg).ok())
+            .unwrap_or_default();
+        if let Some(mut source) = hello.assets {
+            // Packs served by the game server are reached through the same host
+            let host = self
+                .server
+                .rsplit_once(':')
+                .map_or(self.server.as_str(), |(host, _)| host);
+            source.url = source.url.replace(SERVER_HOST, host);
+            if source != ctx.asset_source {
+                if ctx.asset_source_locked {
+                    warn!(
+                        "Server uses assets from {}, keeping the ones set by the environment",
+                        source.url
+                    );
+                } else if ctx.offline {
+                    warn!(
+                        "Server uses assets from {}, keeping the local ones while offline",
+                        source.url
+                    );
+                } else {
+                    return Transition::Replace(Box::new(AssetSyncScene::new(stream, source)));
+                }
+            }
+            // Progress made against other quests can't be played on this server
+            if let Some(commit) = &source.commit {
+                if !assets_match(&ctx.asset_path, commit) {
+                    return Transition::Replace(Box::new(MessageScene::error(tr(
+                        "error.asset_mismatch",
+                    ))));
+                }
+            }
+        }
+        Transition::Replace(Box::new(LoginScene::new(stream)))
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(GRAY);
+    }
+}
+
+/// Whether the local assets can be played on a server pinned to `commit`.
+fn assets_match(asset_path: &str, commit: &str) -> bool {
+    match is_compatible(asset_path, commit) {
+        Ok(true) => {
+            if !checked_out_commit(asset_path).is_some_and(|head| head.starts_with(commit)) {
+                warn!("Assets aren't at the server's commit {}, but its content matches", commit);
+            }
+            true
+        }
+        Ok(false) => {
+            error!("Assets don't match the server's commit {}", commit);
+            false
+        }
+        Err(e) => {
+            error!("Couldn't compare the assets with the server's commit {}: {}", commit, e);
+            false
+        }
+    }
+}
+
+/// Switches the assets to the source a server advertises and reloads the content before
+/// logging in.
+pub struct AssetSyncScene {
+    stream: Option<Stream>,
+    source: AssetSource,
+    updated: bool,
+    cancelled: bool,
+}
+
+impl AssetSyncScene {
+    pub fn new(stream: Stream, source: AssetSource) -> Self {
+        AssetSyncScene {
+            stream: Some(stream),
+            source,
+            updated: false,
+            cancelled: false,
+        }
+    }
+}
+
+impl Scene for AssetSyncScene {
+    fn enter<'a>(&'a mut self, ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        Box::pin(async move {
+            info!(
+                "Updating assets from {} ({})",
+                &self.source.url,
+                self.source.reference()
+            );
+            let result = show_update_progress(
+                &ctx.theme,
+                AssetUpdate::start(&ctx.asset_path, &self.source),
+                tr("status.updating_assets"),
+                tr("common.cancel"),
+            )
+            .await;
+            if let Err(e) = result {
+                self.cancelled = matches!(e, UpdateError::Cancelled);
+                error!("Error updating assets: {}", e);
+                return;
+            }
+            ctx.asset_source = self.source.clone();
+            if let Err(e) = ctx.reload_content().await {
+                error!("Error loading the updated content: {}", e);
+                return;
+            }
+            self.updated = true;
+        })
+    }
+
+    fn update(&mut self, _ctx: &mut GameContext) -> Transition {
+        if self.cancelled {
+            return Transition::Replace(Box::new(ServerSelectScene::new()));
+        }
+        if !self.updated {
+            return Transition::Replace(Box::new(MessageScene::error(tr("error.asset_update"))));
+        }
+        match self.stream.take() {
+            Some(stream) => Transition::Replace(Box::new(LoginScene::new(stream))),
+            None => Transition::None,
+        }
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(GRAY);
+    }
+}
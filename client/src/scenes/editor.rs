@@ -0,0 +1,651 @@
+use std::{fs, path::Path};
+
+use common::{
+    lint::lint_dir,
+    markup::Markup,
+    quest_data::{
+        questlines_to_json, GameData, Quest, QuestCompletion, Questline, DEFAULT_REACH_RADIUS,
+    },
+    ClientState,
+};
+use macroquad::{
+    prelude::*,
+    ui::{hash, root_ui, widgets, Id, Ui},
+};
+
+use crate::{
+    scenes::{message_popup::MessageScene, GameContext, Scene, SceneFuture, Transition},
+    ui::dialog::draw_dialog_window,
+};
+
+const QUESTLINES_FILE: &str = "questlines.json";
+
+/// Completions a line can have, in the order the picker shows them.
+const COMPLETIONS: [&str; 8] = [
+    "None",
+    "Talk to",
+    "Interact",
+    "Reach location",
+    "Collect items",
+    "Visit buildings",
+    "Answer question",
+    "Timer",
+];
+
+/// Longest dialog shown in the list of lines.
+const SUMMARY_LENGTH: usize = 40;
+
+/// Edits `questlines.json` in the game, opened with `--editor`: lists the questlines and
+/// their lines, edits the selected line, previews it in the dialog window and saves the file
+/// back in the layout `questlines_to_json` gives it.
+///
+/// The editor is for content authors, so it isn't translated. Only lines with a node can be
+/// translated, since a line's position changes when it's moved; the lint run after saving
+/// reports translated lines that need one.
+#[derive(Default)]
+pub struct EditorScene {
+    questlines: Vec<Questline>,
+    /// The file as last loaded or saved, to tell whether there are unsaved changes.
+    saved: String,
+    /// Ids of the objects placed in the content, to pick targets from.
+    objects: Vec<String>,
+    locations: Vec<String>,
+    questline: usize,
+    line: Option<usize>,
+    questline_name: String,
+    form: LineForm,
+    preview: bool,
+    confirm_quit: bool,
+    status: Option<String>,
+    load_error: Option<String>,
+}
+
+/// Text fields for the values of the selected line that aren't plain strings.
+#[derive(Default)]
+struct LineForm {
+    quest_id: String,
+    quest_name: String,
+    node: String,
+    goto: String,
+    items: String,
+    count: String,
+    options: String,
+    answer: String,
+    seconds: String,
+}
+
+impl LineForm {
+    fn new(line: &Quest) -> Self {
+        let mut form = LineForm {
+            quest_id: line.quest_id.map(|id| id.to_string()).unwrap_or_default(),
+            quest_name: line.quest_name.clone().unwrap_or_default(),
+            node: line.node.clone().unwrap_or_default(),
+            goto: line.goto.clone().unwrap_or_default(),
+            ..Default::default()
+        };
+        match &line.completion {
+            Some(QuestCompletion::CollectItem { items }) => form.items = items.join(", "),
+            Some(QuestCompletion::VisitBuildings { count, .. }) => form.count = count.to_string(),
+            Some(QuestCompletion::AnswerQuestion {
+                options, answer, ..
+            }) => {
+                form.options = options.join(" | ");
+                form.answer = answer.to_string();
+            }
+            Some(QuestCompletion::Timer { seconds }) => form.seconds = seconds.to_string(),
+            _ => (),
+        }
+        form
+    }
+
+    /// Copies the fields into `line`, keeping the old value of numbers that don't parse.
+    fn apply(&self, line: &mut Quest) {
+        if self.quest_id.trim().is_empty() {
+            line.quest_id = None;
+        } else if let Ok(id) = self.quest_id.trim().parse() {
+            line.quest_id = Some(id);
+        }
+        line.quest_name = non_empty(&self.quest_name);
+        line.node = non_empty(&self.node);
+        line.goto = non_empty(&self.goto);
+        match &mut line.completion {
+            Some(QuestCompletion::CollectItem { items }) => *items = split_list(&self.items, ','),
+            Some(QuestCompletion::VisitBuildings { count, .. }) => {
+                if let Ok(value) = self.count.trim().parse() {
+                    *count = value;
+                }
+            }
+            Some(QuestCompletion::AnswerQuestion {
+                options, answer, ..
+            }) => {
+                *options = split_list(&self.options, '|');
+                if let Ok(value) = self.answer.trim().parse() {
+                    *answer = value;
+                }
+            }
+            Some(QuestCompletion::Timer { seconds }) => {
+                if let Ok(value) = self.seconds.trim().parse() {
+                    *seconds = value;
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl EditorScene {
+    fn select_questline(&mut self, index: usize) {
+        self.questline = index;
+        self.line = None;
+        self.questline_name = self
+            .questlines
+            .get(index)
+            .and_then(|questline| questline.name.clone())
+            .unwrap_or_default();
+    }
+
+    fn select_line(&mut self, index: Option<usize>) {
+        self.line = index;
+        if let Some(line) = self.selected_line() {
+            self.form = LineForm::new(line);
+        }
+    }
+
+    fn selected_line(&self) -> Option<&Quest> {
+        self.questlines.get(self.questline)?.quests.get(self.line?)
+    }
+
+    fn questline_window(&mut self, pos: Vec2, size: Vec2) {
+        let id = hash!("editor", "questlines");
+        let questlines = &self.questlines;
+        let current = self.questline;
+        let mut select = None;
+        let mut add = false;
+        widgets::Window::new(id, pos, size)
+            .titlebar(false)
+            .movable(false)
+            .ui(&mut root_ui(), |ui| {
+                ui.label(None, "Questlines");
+                for (i, questline) in questlines.iter().enumerate() {
+                    let label = match &questline.name {
+                        Some(name) => format!("{}: {}", questline.id, name),
+                        None => questline.id.to_string(),
+                    };
+                    if widgets::Button::new(label.as_str())
+                        .selected(i == current)
+                        .ui(ui)
+                    {
+                        select = Some(i);
+                    }
+                }
+                ui.label(None, "");
+                add = ui.button(None, "New questline");
+            });
+        root_ui().move_window(id, pos);
+
+        if add {
+            let id = self
+                .questlines
+                .iter()
+                .map(|ql| ql.id + 1)
+                .max()
+                .unwrap_or(0);
+            self.questlines.push(Questline {
+                id,
+                name: None,
+                prerequisites: None,
+                branch: None,
+                quests: vec![Quest {
+                    quest_id: Some(0),
+                    ..Default::default()
+                }],
+            });
+            select = Some(self.questlines.len() - 1);
+        }
+        if let Some(index) = select {
+            self.select_questline(index);
+        }
+    }
+
+    fn lines_window(&mut self, pos: Vec2, size: Vec2) {
+        let id = hash!("editor", "lines");
+        let Some(questline) = self.questlines.get_mut(self.questline) else {
+            return;
+        };
+        let name = &mut self.questline_name;
+        let current = self.line;
+        let mut select = None;
+        let (mut add, mut up, mut down, mut remove) = (false, false, false, false);
+        widgets::Window::new(id, pos, size)
+            .titlebar(false)
+            .movable(false)
+            .ui(&mut root_ui(), |ui| {
+                ui.label(None, &format!("Questline {}", questline.id));
+                widgets::InputText::new(hash!("editor", "questline_name", questline.id))
+                    .label("Name")
+                    .ui(ui, name);
+                questline.name = non_empty(name);
+                ui.label(None, "");
+                for (i, line) in questline.quests.iter().enumerate() {
+                    let mut label = match line.quest_id {
+                        Some(quest_id) => format!("[quest {}] ", quest_id),
+                        None => String::new(),
+                    };
+                    label.push_str(&format!("{}: {}", line.speaker, shorten(&line.dialog)));
+                    if widgets::Button::new(label.as_str())
+                        .selected(Some(i) == current)
+                        .ui(ui)
+                    {
+                        select = Some(i);
+                    }
+                }
+                ui.label(None, "");
+                add = ui.button(None, "Add line");
+                if current.is_some() {
+                    ui.same_line(0.);
+                    up = ui.button(None, "Move up");
+                    ui.same_line(0.);
+                    down = ui.button(None, "Move down");
+                    ui.same_line(0.);
+                    remove = ui.button(None, "Remove");
+                }
+            });
+        root_ui().move_window(id, pos);
+
+        let lines = &mut questline.quests;
+        match current {
+            Some(i) if up && i > 0 => {
+                lines.swap(i, i - 1);
+                select = Some(i - 1);
+            }
+            Some(i) if down && i + 1 < lines.len() => {
+                lines.swap(i, i + 1);
+                select = Some(i + 1);
+            }
+            Some(i) if remove => {
+                lines.remove(i);
+                self.line = None;
+            }
+            _ => (),
+        }
+        if add {
+            // New lines go after the selected one, spoken by the same speaker
+            let index = current.map_or(lines.len(), |i| i + 1);
+            let speaker = current
+                .and_then(|i| lines.get(i))
+                .map(|line| line.speaker.clone())
+                .unwrap_or_default();
+            lines.insert(
+                index,
+                Quest {
+                    speaker,
+                    ..Default::default()
+                },
+            );
+            select = Some(index);
+        }
+        if select.is_some() {
+            self.select_line(select);
+        }
+    }
+
+    fn line_window(&mut self, pos: Vec2, size: Vec2) {
+        let id = hash!("editor", "line");
+        // Each line gets its own widgets, so text being edited doesn't carry over
+        let line_key = (self.questline, self.line);
+        let line = match self.line {
+            Some(i) => self
+                .questlines
+                .get_mut(self.questline)
+                .and_then(|questline| questline.quests.get_mut(i)),
+            None => None,
+        };
+        let form = &mut self.form;
+        let objects = &self.objects;
+        let locations = &self.locations;
+        widgets::Window::new(id, pos, size)
+            .titlebar(false)
+            .movable(false)
+            .ui(&mut root_ui(), |ui| {
+                let Some(line) = line else {
+                    ui.label(None, "Pick a line to edit it");
+                    return;
+                };
+                widgets::InputText::new(hash!("editor", "speaker", line_key))
+                    .label("Speaker")
+                    .ui(ui, &mut line.speaker);
+                ui.label(None, "Dialog");
+                widgets::Editbox::new(
+                    hash!("editor", "dialog", line_key),
+                    vec2(size.x - 30., 100.),
+                )
+                .ui(ui, &mut line.dialog);
+                for text in [&line.speaker, &line.dialog] {
+                    if let Err(e) = Markup::parse(text) {
+                        ui.label(None, &format!("Markup error: {}", e));
+                    }
+                }
+                widgets::InputText::new(hash!("editor", "quest_id", line_key))
+                    .label("Quest id")
+                    .filter_numbers()
+                    .ui(ui, &mut form.quest_id);
+                widgets::InputText::new(hash!("editor", "quest_name", line_key))
+                    .label("Quest name")
+                    .ui(ui, &mut form.quest_name);
+                widgets::InputText::new(hash!("editor", "node", line_key))
+                    .label("Node")
+                    .ui(ui, &mut form.node);
+                widgets::InputText::new(hash!("editor", "goto", line_key))
+                    .label("Go to node")
+                    .ui(ui, &mut form.goto);
+
+                let kind = completion_kind(&line.completion);
+                let mut picked = kind;
+                ui.combo_box(
+                    hash!("editor", "completion", line_key),
+                    "Completion",
+                    &COMPLETIONS,
+                    &mut picked,
+                );
+                if picked != kind {
+                    line.completion = new_completion(picked);
+                    *form = LineForm::new(line);
+                }
+                match &mut line.completion {
+                    Some(QuestCompletion::TalkTo { who } | QuestCompletion::Interact { who }) => {
+                        pick(
+                            ui,
+                            hash!("editor", "object", line_key),
+                            "Object",
+                            objects,
+                            who,
+                        );
+                    }
+                    Some(QuestCompletion::ReachLocation { loc_id, .. }) => {
+                        pick(
+                            ui,
+                            hash!("editor", "location", line_key),
+                            "Location",
+                            locations,
+                            loc_id,
+                        );
+                    }
+                    Some(QuestCompletion::CollectItem { .. }) => {
+                        widgets::InputText::new(hash!("editor", "items", line_key))
+                            .label("Items (comma separated)")
+                            .ui(ui, &mut form.items);
+                    }
+                    Some(QuestCompletion::VisitBuildings { .. }) => {
+                        widgets::InputText::new(hash!("editor", "count", line_key))
+                            .label("Buildings to visit")
+                            .filter_numbers()
+                            .ui(ui, &mut form.count);
+                    }
+                    Some(QuestCompletion::AnswerQuestion { question, .. }) => {
+                        widgets::InputText::new(hash!("editor", "question", line_key))
+                            .label("Question")
+                            .ui(ui, question);
+                        widgets::InputText::new(hash!("editor", "options", line_key))
+                            .label("Options (separated by |)")
+                            .ui(ui, &mut form.options);
+                        widgets::InputText::new(hash!("editor", "answer", line_key))
+                            .label("Answer (from 0)")
+                            .filter_numbers()
+                            .ui(ui, &mut form.answer);
+                    }
+                    Some(QuestCompletion::Timer { .. }) => {
+                        widgets::InputText::new(hash!("editor", "seconds", line_key))
+                            .label("Seconds")
+                            .filter_numbers()
+                            .ui(ui, &mut form.seconds);
+                    }
+                    None => (),
+                }
+                form.apply(line);
+            });
+        root_ui().move_window(id, pos);
+    }
+
+    /// Draws the buttons along the top. Returns `true` once the editor should close.
+    fn toolbar(&mut self, asset_path: &str, pos: Vec2, size: Vec2) -> bool {
+        let id = hash!("editor", "toolbar");
+        let status = &self.status;
+        let has_line = self.line.is_some();
+        let (mut save, mut preview, mut quit) = (false, false, false);
+        widgets::Window::new(id, pos, size)
+            .titlebar(false)
+            .movable(false)
+            .ui(&mut root_ui(), |ui| {
+                save = ui.button(None, "Save");
+                if has_line {
+                    ui.same_line(0.);
+                    preview = ui.button(None, "Preview");
+                }
+                ui.same_line(0.);
+                quit = ui.button(None, "Quit");
+                if let Some(message) = status {
+                    ui.same_line(0.);
+                    ui.label(None, message);
+                }
+            });
+        root_ui().move_window(id, pos);
+
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if save || (ctrl && is_key_pressed(KeyCode::S)) {
+            self.save(asset_path);
+        }
+        self.preview |= preview;
+        if !quit {
+            return false;
+        }
+        // Quitting with unsaved changes needs a second press
+        if self.confirm_quit || questlines_to_json(&self.questlines) == self.saved {
+            return true;
+        }
+        self.confirm_quit = true;
+        self.status = Some(String::from(
+            "Unsaved changes, press Quit again to discard them",
+        ));
+        false
+    }
+
+    fn save(&mut self, asset_path: &str) {
+        let json = questlines_to_json(&self.questlines);
+        // The game refuses content with broken markup, so it isn't written
+        if let Err(e) = GameData::from_json(&json, "[]") {
+            error!("Not saving the questlines: {}", e);
+            self.status = Some(String::from("Not saved, see the log for errors"));
+            return;
+        }
+        let path = format!("{}{}", asset_path, QUESTLINES_FILE);
+        if let Err(e) = fs::write(&path, &json) {
+            error!("Couldn't save {}: {}", path, e);
+            self.status = Some(format!("Couldn't save: {}", e));
+            return;
+        }
+        info!("Saved {}", path);
+        self.saved = json;
+        self.confirm_quit = false;
+        let issues = lint_dir(Path::new(asset_path)).unwrap_or_default();
+        for issue in &issues {
+            warn!("{}", issue);
+        }
+        self.status = Some(if issues.is_empty() {
+            String::from("Saved")
+        } else {
+            format!("Saved, {} problems found, see the log", issues.len())
+        });
+    }
+
+    /// Shows the selected line the way the game does, fully typed out.
+    fn draw_preview(&self, ctx: &GameContext) {
+        let Some(line) = self.selected_line() else {
+            return;
+        };
+        let state = ClientState::new("Player");
+        let parse = |text: &str| Markup::parse(text).unwrap_or_default();
+        let speaker = parse(&line.speaker).plain_text(&state);
+        let markup = parse(&line.dialog);
+        let runs = markup.reveal(&state, f64::MAX, 0.);
+        let choices: Vec<String> = line
+            .choices
+            .iter()
+            .map(|choice| parse(&choice.text).plain_text(&state))
+            .collect();
+        draw_dialog_window(
+            &ctx.dialog_resources,
+            &markup,
+            &speaker,
+            &runs,
+            usize::MAX,
+            &choices,
+        );
+    }
+}
+
+impl Scene for EditorScene {
+    fn enter<'a>(&'a mut self, ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        // Edit the file itself rather than the loaded content, which has been translated
+        let path = format!("{}{}", ctx.asset_path, QUESTLINES_FILE);
+        let loaded: Result<Vec<Questline>, String> = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()));
+        match loaded {
+            Ok(questlines) => {
+                self.questlines = questlines;
+                self.saved = questlines_to_json(&self.questlines);
+            }
+            Err(e) => {
+                error!("Couldn't read {}: {}", path, e);
+                self.load_error = Some(format!("Couldn't read {}", QUESTLINES_FILE));
+            }
+        }
+        self.select_questline(0);
+
+        self.objects = ctx
+            .game_data
+            .object_locations
+            .iter()
+            .map(|obj| obj.object_id.clone())
+            .collect();
+        self.objects.sort();
+        self.objects.dedup();
+        self.locations = std::iter::once(String::from("outside"))
+            .chain(ctx.maps.insides.iter().map(|map| map.loc_id.clone()))
+            .collect();
+        self.locations.sort();
+        self.locations.dedup();
+        Box::pin(async {})
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if let Some(e) = self.load_error.take() {
+            return Transition::Replace(Box::new(MessageScene::new(&e, RED, None)));
+        }
+        if self.preview {
+            if is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::Escape) {
+                self.preview = false;
+            }
+            self.draw_preview(ctx);
+            return Transition::None;
+        }
+        let (width, height) = (screen_width(), screen_height());
+        root_ui().push_skin(&ctx.default_theme);
+        let quit = self.toolbar(&ctx.asset_path, vec2(0., 0.), vec2(width, 40.));
+        let top = 40.;
+        self.questline_window(vec2(0., top), vec2(width * 0.2, height - top));
+        self.lines_window(vec2(width * 0.2, top), vec2(width * 0.35, height - top));
+        self.line_window(vec2(width * 0.55, top), vec2(width * 0.45, height - top));
+        root_ui().pop_skin();
+        if quit {
+            return Transition::Quit;
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(GRAY);
+        if self.preview {
+            draw_text("Space or Escape to go back", 20., 40., 30., WHITE);
+        }
+    }
+}
+
+/// Picks `value` out of `ids`. Values that aren't in the list are kept until another one is
+/// picked.
+fn pick(ui: &mut Ui, id: Id, label: &str, ids: &[String], value: &mut String) {
+    let variants: Vec<&str> = std::iter::once("(none)")
+        .chain(ids.iter().map(String::as_str))
+        .collect();
+    let current = ids
+        .iter()
+        .position(|known| known.eq_ignore_ascii_case(value))
+        .map_or(0, |i| i + 1);
+    let mut picked = current;
+    ui.combo_box(id, label, &variants, &mut picked);
+    if picked != current && picked > 0 {
+        *value = ids[picked - 1].clone();
+    }
+    if picked == 0 && !value.is_empty() {
+        ui.label(None, &format!("'{}' isn't in the content", value));
+    }
+}
+
+fn completion_kind(completion: &Option<QuestCompletion>) -> usize {
+    match completion {
+        None => 0,
+        Some(QuestCompletion::TalkTo { .. }) => 1,
+        Some(QuestCompletion::Interact { .. }) => 2,
+        Some(QuestCompletion::ReachLocation { .. }) => 3,
+        Some(QuestCompletion::CollectItem { .. }) => 4,
+        Some(QuestCompletion::VisitBuildings { .. }) => 5,
+        Some(QuestCompletion::AnswerQuestion { .. }) => 6,
+        Some(QuestCompletion::Timer { .. }) => 7,
+    }
+}
+
+/// An empty completion of the kind at `kind` in `COMPLETIONS`.
+fn new_completion(kind: usize) -> Option<QuestCompletion> {
+    let completion = match kind {
+        1 => QuestCompletion::TalkTo { who: String::new() },
+        2 => QuestCompletion::Interact { who: String::new() },
+        3 => QuestCompletion::ReachLocation {
+            loc_id: String::new(),
+            position: None,
+            radius: DEFAULT_REACH_RADIUS,
+        },
+        4 => QuestCompletion::CollectItem { items: Vec::new() },
+        5 => QuestCompletion::VisitBuildings {
+            count: 1,
+            loc_ids: None,
+        },
+        6 => QuestCompletion::AnswerQuestion {
+            question: String::new(),
+            options: Vec::new(),
+            answer: 0,
+        },
+        7 => QuestCompletion::Timer { seconds: 1. },
+        _ => return None,
+    };
+    Some(completion)
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn split_list(text: &str, separator: char) -> Vec<String> {
+    text.split(separator)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(SUMMARY_LENGTH) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
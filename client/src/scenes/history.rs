@@ -0,0 +1,62 @@
+use macroquad::{
+    prelude::*,
+    ui::{root_ui, widgets},
+};
+
+use crate::{
+    locale::tr,
+    scenes::{journal::draw_dimmer, GameContext, Scene, Transition},
+    ui::dialog::wrap_text,
+};
+
+/// Shows the most recent dialog lines over the map until the player closes it with H or
+/// Escape.
+pub struct HistoryScene;
+
+impl Scene for HistoryScene {
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if is_key_pressed(KeyCode::H) || is_key_pressed(KeyCode::Escape) {
+            return Transition::Pop;
+        }
+        let history = &ctx.session.as_ref().expect("History needs a session").dialog_history;
+        root_ui().push_skin(&ctx.theme);
+        let window_pos = vec2(screen_width() / 10.0, screen_height() / 10.0);
+        let window_size = vec2(screen_width() / 10.0 * 8.0, screen_height() / 10.0 * 8.0);
+        widgets::Window::new(
+            0b01101000011010010111001101110100011011110111001001111001,
+            window_pos,
+            window_size,
+        )
+        .label("History")
+        .titlebar(false)
+        .ui(&mut root_ui(), |ui| {
+            let title = tr("history.title");
+            let label_size = ui.calc_size(title);
+            ui.label(vec2(window_size.x / 2.0 - (label_size.x / 2.0), 0.0), title);
+            ui.label(None, "");
+            if history.is_empty() {
+                ui.label(None, tr("history.empty"));
+            }
+            for (speaker, text) in history.lines() {
+                let line = format!("{}: {}", speaker, text);
+                for line in wrap_text(ui, &line, window_size.x - 40.0) {
+                    ui.label(None, &line);
+                }
+            }
+        });
+        root_ui().move_window(
+            0b01101000011010010111001101110100011011110111001001111001,
+            window_pos,
+        );
+        root_ui().pop_skin();
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        draw_dimmer();
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -1,17 +1,31 @@
+use std::rc::Rc;
+
 use ::glam::f32::vec2 as glam_vec2;
-use common::ClientState;
-use macroquad::{
-    prelude::*,
-    ui::{root_ui, widgets, Skin},
+use common::{
+    map_data::MapMeta,
+    map_objects::Area,
+    quest_data::QuestCompletion,
+    quest_engine::{focus_questline, pending_branches, start_questline, QuestEvent},
 };
+use macroquad::{prelude::*, ui::root_ui};
 use macroquad_platformer::*;
-use macroquad_tiled as tiled;
 // use openssl::ssl::SslStream;
 
 use crate::{
-    map_data::MapMeta,
-    quest_data::{get_quest_data, GameData, Quest, Questline},
-    ui::dialog::render_dialog,
+    assets::MapAssets,
+    camera::FollowCamera,
+    locale::{tr, tr_format},
+    scenes::{
+        history::HistoryScene,
+        journal::JournalScene,
+        message_popup::{draw_loading, MessageScene},
+        outside::{FastTravelScene, CAMPUS_PICTURE},
+        world_scene, GameContext, Scene, SceneFuture, Transition,
+    },
+    ui::{
+        dialog::{render_dialog, render_question},
+        quests::{render_branch_choice, render_quest_status},
+    },
 };
 
 struct Player {
@@ -29,194 +43,261 @@ struct Collision {
 
 const DEBUG: bool = true;
 
-pub async fn render_inside(
-    _dialog_theme: &Skin,
-    quests_theme: &Skin,
-    asset_path: &str,
-    map_data: &Vec<MapMeta>,
-    game_data: &GameData,
-    state: &mut ClientState,
-) {
-    let map_id = map_data
-        .iter()
-        .position(|v| &v.loc_id == &state.location)
-        .unwrap();
-    let map = map_data.get(map_id).unwrap();
-    let asset_path = asset_path.to_string();
-    let mut map_path = asset_path.clone();
-    map_path.push_str("maps/");
+/// A loaded map, with the player in it.
+struct LoadedMap {
+    assets: Rc<MapAssets>,
+    map_size: Vec2,
+    world: World,
+    player: Player,
+    camera: FollowCamera,
+}
 
-    info!("Load Tilesets");
-    let mut tilesets = Vec::new();
-    for (name, path) in &map.tilemap_texture_mappings {
-        let mut tileset_path = map_path.clone();
-        tileset_path.push_str(path);
-        let tileset = load_texture(&tileset_path).await.unwrap();
-        tileset.set_filter(FilterMode::Nearest);
-        tilesets.push((name.as_str(), tileset));
+/// Walkable tiled map of the current location, either a building or the campus. Shows a
+/// loading screen until the map is in the asset cache.
+pub struct MapScene {
+    location: String,
+    /// Asset cache key of the map.
+    key: String,
+    map: Option<LoadedMap>,
+    // Walk-on exits only work once the player has stepped off the one they arrived on
+    exits_armed: bool,
+    /// The map is missing or couldn't be loaded.
+    failed: bool,
+    esc_timeout: f64,
+    open_time: f64,
+    done_dialog: bool,
+}
+
+impl MapScene {
+    pub fn new(location: &str) -> Self {
+        MapScene {
+            location: location.to_string(),
+            key: String::new(),
+            map: None,
+            exits_armed: false,
+            failed: false,
+            esc_timeout: 0.,
+            open_time: 0.,
+            done_dialog: true,
+        }
     }
 
-    info!("Load Tilemap");
-    let mut tiled_map_path = map_path.clone();
-    tiled_map_path.push_str(&map.tilemap_path);
-    let tiled_map_json = load_string(&tiled_map_path).await.unwrap();
-    let tiled_map = tiled::load_map(&tiled_map_json, tilesets.as_slice(), &[]).unwrap();
+    fn on_campus(&self) -> bool {
+        self.location.eq_ignore_ascii_case("outside")
+    }
 
-    info!("Calculate Collisions & Layer Order");
-    let mut map_width = 0;
-    let mut map_height = 0;
-    let mut static_colliders = vec![];
-    let mut first = true;
-    let mut layer_order = vec![""; tiled_map.layers.len()];
-    for (name, layer) in &tiled_map.layers {
-        {
-            let layer_id = name.split_once('_').unwrap().0;
-            let layer_id: usize = layer_id.parse().unwrap();
-            layer_order[layer_id] = name.as_str();
+    /// Sets the map up once the asset cache has loaded it.
+    fn poll_loaded(&mut self, ctx: &mut GameContext) {
+        if self.map.is_some() {
+            return;
         }
-        if name
-            .to_ascii_lowercase()
-            .contains(&"collide".to_ascii_lowercase())
-        {
-            if first {
-                map_width = layer.width;
-                map_height = layer.height;
-            }
-            for (x, y, tile) in tiled_map.tiles(name, None) {
-                if first {
-                    static_colliders.push(if tile.is_some() {
-                        Tile::Solid
-                    } else {
-                        Tile::Empty
-                    });
-                } else {
-                    if tile.is_some() {
-                        static_colliders[((y * layer.width) + x) as usize] = Tile::Solid;
-                    }
+        if ctx.assets.failed(&self.key) {
+            error!("Unable to load map '{}'", self.key);
+            self.failed = true;
+            return;
+        }
+        if let Some(assets) = ctx.assets.map(&self.key) {
+            self.map = Some(self.build(ctx, assets));
+            self.esc_timeout = get_time();
+            self.open_time = get_time();
+        }
+    }
+
+    /// Leaves a map that can't be shown for the campus, or for the server selector when it's
+    /// the campus that's broken.
+    fn fall_back(&self, ctx: &mut GameContext) -> Transition {
+        let msg = tr_format("error.map", &[&self.location]);
+        if self.on_campus() {
+            ctx.session().save();
+            return Transition::Replace(Box::new(MessageScene::error(&msg)));
+        }
+        let state = &mut ctx.session().state;
+        state.spawn = Some(self.location.clone());
+        state.location = String::from("outside");
+        state.pos = glam_vec2(0., 0.);
+        let campus = world_scene(&ctx.maps, "outside");
+        Transition::Replace(Box::new(MessageScene::new(&msg, RED, Some(campus))))
+    }
+
+    fn build(&self, ctx: &mut GameContext, assets: Rc<MapAssets>) -> LoadedMap {
+        let map = ctx.maps.get_map(&self.location).unwrap();
+        let tiled_map = &assets.tiled_map;
+        let map_objects = &assets.map_objects;
+        if map_objects.exits.is_empty() && !self.on_campus() {
+            warn!("'{}' has no exits, ESC leaves the building", map.loc_id);
+        }
+
+        info!("Calculate Collisions");
+        let (map_width, map_height) = match map_objects.layers.first() {
+            Some(layer) => (
+                tiled_map.layers[&layer.name].width,
+                tiled_map.layers[&layer.name].height,
+            ),
+            None => (0, 0),
+        };
+        let mut static_colliders = vec![Tile::Empty; (map_width * map_height) as usize];
+        for layer in map_objects.layers.iter().filter(|l| l.collides) {
+            for (x, y, tile) in tiled_map.tiles(&layer.name, None) {
+                if tile.is_some() {
+                    static_colliders[((y * map_width) + x) as usize] = Tile::Solid;
                 }
             }
-            first = false;
+        }
+
+        info!("Add collision to world");
+        let mut world = World::new();
+        world.add_static_tiled_layer(static_colliders, 32., 32., map_width as usize, 1);
+        for area in map_objects.solid_areas() {
+            world.add_solid(
+                vec2(area.pos.x, area.pos.y),
+                area.size.x as i32,
+                area.size.y as i32,
+            );
+        }
+
+        // Setup Player
+        let state = &mut ctx.session.as_mut().expect("Maps need a session").state;
+        let spawn = state.spawn.take();
+        let player_pos = if spawn.is_none() && !state.pos.eq(&glam_vec2(0., 0.)) {
+            glam2mac(state.pos)
+        } else {
+            match map_objects.spawn_point(spawn.as_deref()) {
+                // Spawn points mark where the player's center goes
+                Some(pos) => glam2mac(pos) - vec2(14., 14.),
+                None => glam2mac(map.spawn_location) * vec2(32., 32.),
+            }
+        };
+        let player = Player {
+            collider: world.add_actor(player_pos, 28, 28),
+            speed: vec2(0., 0.),
+        };
+        let map_size = vec2(map_width as f32 * 32., map_height as f32 * 32.);
+        let camera = FollowCamera::new(map_size, player_pos + vec2(14., 14.), &ctx.settings);
+
+        // Start loading where the player is likely to go next
+        let mut next_maps: Vec<&MapMeta> = map_objects
+            .exits
+            .iter()
+            .filter_map(|exit| ctx.maps.get_map(&exit.target))
+            .collect();
+        if let Some(destination) = ctx.quest_destination() {
+            next_maps.extend(ctx.maps.get_map(&destination));
+        }
+        let fast_travel: &[&str] = if self.on_campus() && ctx.settings.fast_travel {
+            &[CAMPUS_PICTURE]
+        } else {
+            &[]
+        };
+        ctx.assets.preload(fast_travel, &next_maps);
+
+        LoadedMap {
+            assets,
+            map_size,
+            world,
+            player,
+            camera,
         }
     }
+}
 
-    info!("Add collision to world");
-    let mut world = World::new();
-    world.add_static_tiled_layer(static_colliders, 32., 32., map_width as usize, 1);
+impl Scene for MapScene {
+    fn enter<'a>(&'a mut self, ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        let Some(map) = ctx.maps.get_map(&self.location) else {
+            error!("No map for location '{}'", self.location);
+            self.failed = true;
+            return Box::pin(async {});
+        };
+        self.key = ctx.assets.load_map(map);
+        let session = ctx.session.as_mut().expect("Maps need a session");
+        let state = &mut session.state;
+        // Dialog left open when the game quit on the campus shows again
+        self.done_dialog = !self.on_campus()
+            || state.current_questline_id != 0
+            || state.current_quest_id != 0;
+        if session.quest_engine.handle(
+            &ctx.game_data,
+            state,
+            QuestEvent::EnterLocation(&self.location),
+        ) {
+            self.done_dialog = false;
+            state.dialog_offset += 1;
+        }
+        Box::pin(async {})
+    }
 
-    // Setup Player
-    let player_pos = if state.pos.eq(&glam_vec2(0., 0.)) {
-        glam2mac(map.spawn_location) * vec2(32., 32.)
-    } else {
-        glam2mac(state.pos)
-    };
-    let mut player = Player {
-        collider: world.add_actor(player_pos, 28, 28),
-        speed: vec2(0., 0.),
-    };
+    fn exit(&mut self, ctx: &mut GameContext) {
+        ctx.assets.release(&self.key);
+    }
 
-    let mut open_time = get_time();
-    let mut done_dialog = true;
+    fn reload(&mut self, ctx: &mut GameContext) {
+        let Some(map) = ctx.maps.get_map(&self.location) else {
+            error!("'{}' no longer has a map, keeping the old one", self.location);
+            return;
+        };
+        self.key = ctx.assets.load_map(map);
+        // Put the player back where they were once the new map is loaded
+        if let Some(loaded) = self.map.take() {
+            let pos = loaded.world.actor_pos(loaded.player.collider);
+            let state = &mut ctx.session().state;
+            state.pos = glam_vec2(pos.x, pos.y);
+            state.spawn = None;
+        }
+    }
 
-    loop {
-        // Relevant Objects
-        let mut relevant_objects = Vec::new();
-        for obj in &game_data.object_locations {
-            let mut relevant = false;
-            if obj.loc_id == state.location {
-                if obj.relevant_quest_ids.is_none() {
-                    relevant = true;
-                } else {
-                    let relevant_quest_ids = obj.relevant_quest_ids.clone().unwrap();
-                    if relevant_quest_ids.contains(&(state.current_questline_id+state.current_quest_id)) {
-                        relevant = true;
-                    }
-                }
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        self.poll_loaded(ctx);
+        if self.failed {
+            return self.fall_back(ctx);
+        }
+        let on_campus = self.on_campus();
+        let Some(map) = self.map.as_mut() else {
+            return Transition::None;
+        };
+        if get_time() - self.esc_timeout > 0.25 && is_key_pressed(KeyCode::Escape) {
+            // ESC on the campus quits the game
+            if on_campus {
+                ctx.session().save();
+                let closing = MessageScene::new(tr("status.closing"), RED, None);
+                return Transition::Reset(Box::new(closing));
             }
-            if relevant {
-                relevant_objects.push(obj);
+            // Maps without exits can still be left with ESC, arriving at the building's
+            // spawn point on the campus if it has one
+            if map.assets.map_objects.exits.is_empty() {
+                let state = &mut ctx.session().state;
+                state.spawn = Some(self.location.clone());
+                state.location = String::from("outside");
+                state.pos = glam_vec2(0., 0.);
+                return Transition::Replace(world_scene(&ctx.maps, "outside"));
             }
         }
-        // Register ESC to leave building (this will change... esc will close the game and there will be a location to walk to to exit the building)
-        if is_key_pressed(KeyCode::Escape) {
-            state.location = String::from("outside");
-            break;
+        // Open the quest journal
+        if is_key_pressed(KeyCode::J) {
+            return Transition::Push(Box::<JournalScene>::default());
         }
-        // Create Camera
-        let camera = Camera2D::from_display_rect(Rect::new(
-            0.0,
-            screen_height(),
-            screen_width(),
-            screen_height() * -1.,
-        ));
-        set_camera(&camera);
-        // Calculate Render Scale
-        let map_size_x = map_width as f32 * 32.;
-        let map_size_y = map_height as f32 * 32.;
-        let scale_x = screen_width() / map_size_x;
-        let scale_y = screen_height() / map_size_y;
-        let scale = scale_x.min(scale_y);
-        let scaled_map_size_x = map_size_x * scale;
-        let scaled_map_size_y = map_size_y * scale;
-        let map_offset_x = (screen_width() - scaled_map_size_x) / 2.;
-        let map_offset_y = (screen_height() - scaled_map_size_y) / 2.;
-        // Setup UI
-        clear_background(BLACK);
-        // Render Tiles
-        for layer_name in &layer_order {
-            tiled_map.draw_tiles(
-                layer_name,
-                Rect::new(
-                    map_offset_x,
-                    map_offset_y,
-                    scaled_map_size_x,
-                    scaled_map_size_y,
-                ),
-                None,
-            );
+        // Open the dialog history
+        if is_key_pressed(KeyCode::H) {
+            return Transition::Push(Box::new(HistoryScene));
         }
-        // Render Objects
-        {
-            for obj in &relevant_objects {
-                tiled_map.spr(
-                    &obj.sprite.sprite_map,
-                    obj.sprite.tile_id,
-                    Rect::new(
-                        map_offset_x + ((obj.position.x * 32.) * scale),
-                        map_offset_y + ((obj.position.y * 32.) * scale),
-                        32. * scale,
-                        32. * scale,
-                    ),
-                );
-            }
+        // Open the fast travel map
+        if on_campus && ctx.settings.fast_travel && is_key_pressed(KeyCode::M) {
+            return Transition::Push(Box::<FastTravelScene>::default());
         }
-        // Render Player
-        {
-            const PLAYER_SPRITE: u32 = 0;
-
-            let pos = world.actor_pos(player.collider);
-            tiled_map.spr(
-                "objects",
-                PLAYER_SPRITE,
-                Rect::new(
-                    map_offset_x + ((pos.x - 2.) * scale),
-                    map_offset_y + ((pos.y - 2.) * scale),
-                    32. * scale,
-                    32. * scale,
-                ),
-            );
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+            map.camera.zoom_in();
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+            map.camera.zoom_out();
         }
         // Debug Locator
-        if DEBUG {
-            if is_key_pressed(KeyCode::P) {
-                let pos = world.actor_pos(player.collider);
-                let pos_x = (pos.x / scale / 32.) as i32;
-                let pos_y = (pos.y / scale / 32.) as i32;
-                info!("Current Block Location: ({}, {})", pos_x, pos_y);
-            }
+        if DEBUG && is_key_pressed(KeyCode::P) {
+            let pos = map.world.actor_pos(map.player.collider);
+            let pos_x = (pos.x / 32.) as i32;
+            let pos_y = (pos.y / 32.) as i32;
+            info!("Current Block Location: ({}, {})", pos_x, pos_y);
         }
         // Calculate Player Movement
         {
-            // let pos = world.actor_pos(player.collider);
+            let player = &mut map.player;
             if is_key_down(KeyCode::Right) || is_key_down(KeyCode::D) {
                 player.speed.x = 1.;
             } else if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
@@ -232,94 +313,171 @@ pub async fn render_inside(
                 player.speed.y = 0.;
             }
 
-            world.move_h(player.collider, player.speed.x * 256. * get_frame_time());
-            world.move_v(player.collider, player.speed.y * -256. * get_frame_time());
+            map.world.move_h(player.collider, player.speed.x * 256. * get_frame_time());
+            map.world.move_v(player.collider, player.speed.y * -256. * get_frame_time());
+        }
+        map.camera.follow(map.world.actor_pos(map.player.collider) + vec2(14., 14.));
+
+        let game_data = &ctx.game_data;
+        let session = ctx.session.as_mut().expect("Maps need a session");
+        let state = &mut session.state;
+        let quest_engine = &mut session.quest_engine;
+        // Exits & Doors
+        {
+            let pos = map.world.actor_pos(map.player.collider);
+            let player_area = Area::new(glam_vec2(pos.x, pos.y), glam_vec2(28., 28.));
+            let exit = map
+                .assets
+                .map_objects
+                .exits
+                .iter()
+                .find(|exit| exit.area.overlaps(&player_area));
+            let take = match exit {
+                None => {
+                    self.exits_armed = true;
+                    None
+                }
+                Some(exit) if exit.door => enter_pressed().then_some(exit),
+                Some(exit) if self.exits_armed => Some(exit),
+                Some(_) => None,
+            };
+            if let Some(exit) = take {
+                info!("Taking exit to '{}'", exit.target);
+                state.location = exit.target.clone();
+                state.spawn = exit.spawn.clone();
+                state.pos = glam_vec2(0., 0.);
+                return Transition::Replace(world_scene(&ctx.maps, &state.location));
+            }
         }
-        // Quest Data
-        let current_quest = get_quest_data(&game_data.questlines, &state)
-            .unwrap_or(Quest {
-                speaker: "".to_string(),
-                dialog: "ERROR FINDING QUEST".to_string(),
-                quest_id: Some(state.current_quest_id),
-                quest_name: Some("Error finding quest".to_string()),
-                completion: None,
-            });
         // Render Quest Status Indicators
         {
-            root_ui().push_skin(&quests_theme);
-            render_quest_status(&game_data.questlines, &state);
+            root_ui().push_skin(&ctx.default_theme);
+            if let Some(questline) = render_quest_status(&game_data.questlines, state) {
+                focus_questline(state, questline);
+                self.done_dialog = true;
+            }
+            if self.done_dialog {
+                if let Some(branch) = pending_branches(&game_data.questlines, state).first() {
+                    if let Some(questline) = render_branch_choice(branch) {
+                        start_questline(&game_data.questlines, state, questline);
+                        self.open_time = get_time();
+                        self.done_dialog = state.current_questline_id != questline;
+                    }
+                }
+            }
             root_ui().pop_skin();
         }
-        // Render Dialog
+        // Quest Progress & Dialog
         {
-            if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
-                let quest = current_quest.clone();
-                let completion_type = quest.completion.clone().unwrap().completion_type;
-                if completion_type.eq_ignore_ascii_case("interact") {
-                    let object = quest.completion.unwrap().interact_object_id;
-                    if object.is_some() {
-                        let object_id = object.unwrap();
-                        for obj in &relevant_objects {
-                            if obj.object_id.eq_ignore_ascii_case(&object_id) {
-                                // let obj_location = obj;
-                                let player_pos = world.actor_pos(player.collider) / 32.;
-                                if player_pos.distance(glam2mac(obj.position))< 3. {
-                                    info!("Interacted with '{}'", object_id);
-                                    done_dialog = false;
-                                    state.complete_quest_ids.push(state.current_questline_id+state.current_quest_id);
-                                    state.dialog_offset += 1;
-                                }
-                            }
-                        }
+            let relevant_objects = game_data.relevant_objects(state);
+            let player_pos = map.world.actor_pos(map.player.collider) / 32.;
+            let mut completed =
+                quest_engine.handle(game_data, state, QuestEvent::Tick(get_frame_time()));
+            completed |= quest_engine.handle(
+                game_data,
+                state,
+                QuestEvent::Move(glam_vec2(player_pos.x, player_pos.y)),
+            );
+            if enter_pressed() {
+                for obj in &relevant_objects {
+                    if player_pos.distance(glam2mac(obj.position)) < 3. {
+                        info!("Interacted with '{}'", obj.object_id);
+                        completed |=
+                            quest_engine.handle(game_data, state, QuestEvent::Interact(&obj.object_id));
+                    }
+                }
+            }
+            if self.done_dialog {
+                if let Some(QuestCompletion::AnswerQuestion {
+                    question, options, ..
+                }) = quest_engine.pending_completion(game_data, state)
+                {
+                    root_ui().push_skin(&ctx.default_theme);
+                    if let Some(choice) = render_question(question, options) {
+                        completed |= quest_engine.handle(game_data, state, QuestEvent::Answer(choice));
                     }
+                    root_ui().pop_skin();
                 }
             }
-            if !done_dialog {
-                let f = render_dialog(&game_data.questlines, open_time, state);
-                open_time = f.1;
-                done_dialog = f.0;
+            if completed {
+                self.done_dialog = false;
+                self.open_time = get_time();
+                state.dialog_offset += 1;
+            }
+            if !self.done_dialog {
+                let f = render_dialog(
+                    &game_data.questlines,
+                    &ctx.dialog_resources,
+                    &ctx.settings,
+                    &mut session.dialog_history,
+                    self.open_time,
+                    state,
+                );
+                self.open_time = f.1;
+                self.done_dialog = f.0;
             }
         }
-        //
-        root_ui().pop_skin();
-        next_frame().await
+        Transition::None
     }
-}
 
-fn render_quest_status(questlines: &Vec<Questline>, state: &ClientState) {
-    let quest = questlines
-        .iter()
-        .find(|ql| ql.id == state.current_questline_id)
-        .unwrap()
-        .quests
-        .iter()
-        .find(|q| q.quest_id.is_some() && q.quest_id.unwrap() == state.current_quest_id)
-        .unwrap();
-    let name = quest.quest_name.clone().unwrap();
+    fn draw(&mut self, ctx: &mut GameContext) {
+        self.poll_loaded(ctx);
+        let Some(map) = &self.map else {
+            draw_loading(&ctx.theme, ctx.assets.progress(&self.key));
+            return;
+        };
+        let tiled_map = &map.assets.tiled_map;
+        let map_objects = &map.assets.map_objects;
+        // Create Camera
+        set_camera(&Camera2D::from_display_rect(Rect::new(
+            0.0,
+            screen_height(),
+            screen_width(),
+            -screen_height(),
+        )));
+        clear_background(BLACK);
+        // Render Tiles
+        for layer in &map_objects.layers {
+            tiled_map.draw_tiles(
+                &layer.name,
+                map.camera.world_rect(Vec2::ZERO, map.map_size),
+                None,
+            );
+        }
+        // Render Objects
+        if let Some(session) = &ctx.session {
+            for obj in &ctx.game_data.relevant_objects(&session.state) {
+                tiled_map.spr(
+                    &obj.sprite.sprite_map,
+                    obj.sprite.tile_id,
+                    map.camera.world_rect(glam2mac(obj.position) * 32., vec2(32., 32.)),
+                );
+            }
+        }
+        // Render Player
+        {
+            const PLAYER_SPRITE: u32 = 0;
 
-    let Vec2 {
-        x: width,
-        y: height,
-    } = root_ui().calc_size(&name);
-    let margin = screen_width() * 0.025;
-    let window_width = width + (margin * 2.);
-    let window_height = height + (margin * 2.);
+            let pos = map.world.actor_pos(map.player.collider);
+            tiled_map.spr(
+                "objects",
+                PLAYER_SPRITE,
+                map.camera.world_rect(pos - vec2(2., 2.), vec2(32., 32.)),
+            );
+        }
+        // Debug Exits, shown in developer mode only
+        if ctx.watcher.is_some() {
+            for exit in &map_objects.exits {
+                let rect = map.camera.world_rect(glam2mac(exit.area.pos), glam2mac(exit.area.size));
+                let color = if exit.door { YELLOW } else { GREEN };
+                draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2., color);
+            }
+        }
+    }
+}
 
-    root_ui().move_window(
-        1010,
-        Vec2::new(screen_width() - window_width - margin, margin),
-    );
-    widgets::Window::new(
-        1010,
-        Vec2::new(screen_width() - window_width - margin, margin),
-        Vec2::new(window_width, window_height),
-    )
-    .label("Quests")
-    .titlebar(true)
-    .close_button(false)
-    .ui(&mut root_ui(), |ui| {
-        ui.label(None, &name);
-    });
+fn enter_pressed() -> bool {
+    is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter)
 }
 
 fn glam2mac(vec: ::glam::f32::Vec2) -> Vec2 {
@@ -0,0 +1,103 @@
+use common::journal::{build_journal, JournalQuestline, QuestStatus};
+use macroquad::{
+    prelude::*,
+    ui::{hash, root_ui, widgets},
+};
+
+use crate::{
+    locale::{strings, tr, tr_format},
+    scenes::{GameContext, Scene, SceneFuture, Transition},
+    ui::dialog::wrap_text,
+};
+
+/// Shows the quest journal over the map until the player closes it with J or Escape.
+#[derive(Default)]
+pub struct JournalScene {
+    journal: Vec<JournalQuestline>,
+}
+
+impl Scene for JournalScene {
+    fn enter<'a>(&'a mut self, ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        let state = &ctx.session.as_ref().expect("Journal needs a session").state;
+        self.journal = build_journal(&ctx.game_data, state, strings());
+        Box::pin(async {})
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if is_key_pressed(KeyCode::J) || is_key_pressed(KeyCode::Escape) {
+            return Transition::Pop;
+        }
+        let journal = &self.journal;
+        root_ui().push_skin(&ctx.theme);
+        let window_pos = vec2(screen_width() / 10.0, screen_height() / 10.0);
+        let window_size = vec2(screen_width() / 10.0 * 8.0, screen_height() / 10.0 * 8.0);
+        widgets::Window::new(
+            0b01101010011011110111010101110010011011100110000101101100,
+            window_pos,
+            window_size,
+        )
+        .label("Journal")
+        .titlebar(false)
+        .ui(&mut root_ui(), |ui| {
+            let title = tr("journal.title");
+            let label_size = ui.calc_size(title);
+            ui.label(vec2(window_size.x / 2.0 - (label_size.x / 2.0), 0.0), title);
+            ui.label(None, "");
+            if journal.is_empty() {
+                ui.label(None, tr("journal.empty"));
+            }
+            for questline in journal {
+                ui.tree_node(hash!("journal", questline.id), &questline.name, |ui| {
+                    for quest in &questline.quests {
+                        let status = match quest.status {
+                            QuestStatus::Active => tr("journal.active"),
+                            QuestStatus::Completed => tr("journal.completed"),
+                            QuestStatus::Upcoming => tr("journal.upcoming"),
+                        };
+                        ui.label(None, &format!("{} ({})", quest.name, status));
+                        if quest.status == QuestStatus::Upcoming {
+                            continue;
+                        }
+                        for line in wrap_text(ui, &quest.description, window_size.x * 0.8) {
+                            ui.label(None, &format!("  {}", line));
+                        }
+                        if quest.status == QuestStatus::Active {
+                            if let Some(hint) = &quest.hint {
+                                ui.label(
+                                    None,
+                                    &format!("  {}", tr_format("journal.hint", &[hint])),
+                                );
+                            }
+                        }
+                        for (speaker, dialog) in &quest.history {
+                            let line = format!("{}: {}", speaker, dialog);
+                            for line in wrap_text(ui, &line, window_size.x * 0.8) {
+                                ui.label(None, &format!("    {}", line));
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        root_ui().move_window(
+            0b01101010011011110111010101110010011011100110000101101100,
+            window_pos,
+        );
+        root_ui().pop_skin();
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        draw_dimmer();
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Darkens the scene under an overlay.
+pub fn draw_dimmer() {
+    set_default_camera();
+    draw_rectangle(0., 0., screen_width(), screen_height(), Color::new(0., 0., 0., 0.6));
+}
@@ -1,22 +1,57 @@
-use std::hash::{Hash, Hasher};
+use std::{
+    hash::{Hash, Hasher},
+    net::TcpStream,
+    sync::{Arc, Mutex},
+};
 
-use common::ClientAuth;
+use common::{
+    conn_lib::{read_stream_client, write_flush_client},
+    quest_data::migrate_save,
+    quest_engine::{start_available_questlines, QuestEngine},
+    ClientAuth, ClientState,
+};
 use macroquad::{
     prelude::*,
-    ui::{hash, root_ui, widgets, Skin},
+    ui::{hash, root_ui, widgets},
 };
+use openssl::ssl::SslStream;
 use rs_sha3_256::Sha3_256Hasher;
 
-pub async fn render_login(_theme: &Skin) -> ClientAuth {
-    let mut sha3_256hasher = Sha3_256Hasher::default();
+use crate::{
+    locale::tr,
+    scenes::{
+        message_popup::{show_popup, MessageScene},
+        world_scene, GameContext, Scene, Session, Transition,
+    },
+    ui::dialog::DialogHistory,
+};
+
+pub type Stream = Arc<Mutex<SslStream<TcpStream>>>;
 
-    let mut pwd = String::new();
-    let mut auth = ClientAuth {
-        username: String::new(),
-        pass_hash: u64::MAX,
-    };
+/// Asks for the player's username and password.
+pub struct LoginScene {
+    stream: Option<Stream>,
+    pwd: String,
+    auth: ClientAuth,
+}
+
+impl LoginScene {
+    pub fn new(stream: Stream) -> Self {
+        LoginScene {
+            stream: Some(stream),
+            pwd: String::new(),
+            auth: ClientAuth {
+                username: String::new(),
+                pass_hash: u64::MAX,
+            },
+        }
+    }
+}
 
-    loop {
+impl Scene for LoginScene {
+    fn update(&mut self, _ctx: &mut GameContext) -> Transition {
+        let pwd = &mut self.pwd;
+        let auth = &mut self.auth;
         widgets::Window::new(
             0b0110110001101111011001110110100101101110,
             Vec2::new(screen_width() / 2. - 200., screen_height() / 2. - 75.),
@@ -25,17 +60,18 @@ pub async fn render_login(_theme: &Skin) -> ClientAuth {
         .label("Login")
         .titlebar(false)
         .ui(&mut root_ui(), |ui| {
-            let label_size = ui.calc_size("Login");
+            let label_size = ui.calc_size(tr("login.title"));
             ui.label(None, "");
-            ui.label(Vec2::new(200. - (label_size.x / 2.), 0.), "Login");
+            ui.label(Vec2::new(200. - (label_size.x / 2.), 0.), tr("login.title"));
             widgets::InputText::new(hash!())
                 .size(Vec2::new(350., 35.))
                 .ui(ui, &mut auth.username);
             widgets::InputText::new(hash!())
                 .size(Vec2::new(350., 35.))
                 .password(true)
-                .ui(ui, &mut pwd);
-            if ui.button(Vec2::new(125., 110.), "Submit") {
+                .ui(ui, pwd);
+            if ui.button(Vec2::new(125., 110.), tr("common.submit")) {
+                let mut sha3_256hasher = Sha3_256Hasher::default();
                 pwd.hash(&mut sha3_256hasher);
                 auth.pass_hash = sha3_256hasher.finish();
                 pwd.clear();
@@ -43,9 +79,98 @@ pub async fn render_login(_theme: &Skin) -> ClientAuth {
         });
         root_ui().move_window(0b0110110001101111011001110110100101101110, Vec2::new(screen_width() / 2. - 200., screen_height() / 2. - 75.));
 
-        if auth.pass_hash != u64::MAX {
-            return auth;
+        if self.auth.pass_hash != u64::MAX {
+            if let Some(stream) = self.stream.take() {
+                let auth = ClientAuth {
+                    username: std::mem::take(&mut self.auth.username),
+                    pass_hash: self.auth.pass_hash,
+                };
+                return Transition::Replace(Box::new(AuthScene::new(stream, auth)));
+            }
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(GRAY);
+    }
+}
+
+/// Shows the loading screen while logging in, then starts the session.
+pub struct AuthScene {
+    stream: Stream,
+    auth: Option<ClientAuth>,
+    counter: u32,
+}
+
+impl AuthScene {
+    pub fn new(stream: Stream, auth: ClientAuth) -> Self {
+        AuthScene {
+            stream,
+            auth: Some(auth),
+            counter: 0,
+        }
+    }
+
+    /// Sends the login and waits for the player's state.
+    fn log_in(&self, auth: ClientAuth) -> Result<ClientState, &'static str> {
+        info!("Logging in as '{}'", auth.username);
+        let auth_ser = serde_json::to_string(&auth).expect("Failed to serialize the auth packet");
+        drop(auth);
+        if write_flush_client(self.stream.clone(), auth_ser).is_err() {
+            error!("Couldn't send auth packet");
+            return Err("error.auth_send");
         }
-        next_frame().await
+
+        info!("Getting Client State");
+        let msg = read_stream_client(self.stream.clone()).map_err(|e| {
+            error!("{}", e);
+            "error.connection_closed"
+        })?;
+        let state = serde_json::from_str::<ClientState>(&msg).map_err(|e| {
+            error!("Error parsing server message: {}", e);
+            let _ = self.stream.lock().unwrap().shutdown();
+            "error.no_state"
+        })?;
+        if !state.authenticated {
+            let _ = self.stream.lock().unwrap().shutdown();
+            return Err("error.auth");
+        }
+        Ok(state)
+    }
+}
+
+impl Scene for AuthScene {
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        show_popup(&ctx.theme, String::from(tr("status.loading")));
+        // Let the loading screen show before blocking on the server
+        if self.counter < 3 {
+            self.counter += 1;
+            return Transition::None;
+        }
+        let auth = match self.auth.take() {
+            Some(auth) => auth,
+            None => return Transition::None,
+        };
+        let mut state = match self.log_in(auth) {
+            Ok(state) => state,
+            Err(key) => return Transition::Replace(Box::new(MessageScene::error(tr(key)))),
+        };
+        migrate_save(&mut state, &ctx.game_data.questlines);
+        start_available_questlines(&ctx.game_data.questlines, &mut state);
+        info!("State Received");
+
+        let scene = world_scene(&ctx.maps, &state.location);
+        ctx.session = Some(Session {
+            stream: self.stream.clone(),
+            quest_engine: QuestEngine::new(&state),
+            dialog_history: DialogHistory::new(ctx.settings.history_length),
+            state,
+        });
+        Transition::Reset(scene)
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(GRAY);
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,13 @@
 use macroquad::{prelude::*, ui::{root_ui, widgets, Skin}};
 
+use crate::{
+    asset_updater::{AssetUpdate, UpdateError},
+    locale::tr_format,
+    scenes::{server_select::ServerSelectScene, GameContext, Scene, SceneFuture, Transition},
+};
+
 pub fn show_popup(theme: &Skin, msg: String) {
-    root_ui().push_skin(&theme);
+    root_ui().push_skin(theme);
     let label_size = root_ui().calc_size(&msg);
     widgets::Window::new(
         0b0100001101101111011011100110111001100101011000110111010001101001,
@@ -15,4 +21,99 @@ pub fn show_popup(theme: &Skin, msg: String) {
     });
     root_ui().move_window(0b0100001101101111011011100110111001100101011000110111010001101001, Vec2::new((screen_width() / 2.) - (label_size.x/2.+50.), screen_height() / 2. - 75.));
     root_ui().pop_skin();
-}
\ No newline at end of file
+}
+/// Loading screen with a progress bar, for `progress` from 0 to 1.
+pub fn draw_loading(theme: &Skin, progress: f32) {
+    set_default_camera();
+    clear_background(GRAY);
+    let percent = (progress * 100.).round().to_string();
+    show_popup(theme, tr_format("status.loading_progress", &[&percent]));
+    draw_progress_bar(progress);
+}
+
+fn draw_progress_bar(progress: f32) {
+    let bar_width = screen_width() / 3.;
+    let bar_pos = vec2(screen_width() / 2. - bar_width / 2., screen_height() / 2. + 100.);
+    draw_rectangle(bar_pos.x, bar_pos.y, bar_width, 12., DARKGRAY);
+    draw_rectangle(bar_pos.x, bar_pos.y, bar_width * progress, 12., WHITE);
+}
+
+/// Shows the progress of `update` with `msg` until it finishes or the player clicks `cancel`.
+pub async fn show_update_progress(
+    theme: &Skin,
+    mut update: AssetUpdate,
+    msg: &str,
+    cancel: &str,
+) -> Result<(), UpdateError> {
+    loop {
+        if let Some(result) = update.poll() {
+            return result;
+        }
+        set_default_camera();
+        clear_background(GRAY);
+        let percent = (update.progress().fraction() * 100.).round();
+        show_popup(theme, format!("{} {}%", msg, percent));
+        draw_progress_bar(update.progress().fraction());
+        root_ui().push_skin(theme);
+        let cancel_size = root_ui().calc_size(cancel);
+        let cancel_pos = vec2(
+            screen_width() / 2. - cancel_size.x / 2.,
+            screen_height() / 2. + 130.,
+        );
+        if root_ui().button(cancel_pos, cancel) && !update.progress().is_cancelled() {
+            info!("Cancelling the asset update");
+            update.progress().cancel();
+        }
+        root_ui().pop_skin();
+        next_frame().await
+    }
+}
+
+/// How long messages stay up, in seconds.
+pub const TIMEOUT: f64 = 3.;
+
+/// Shows a message for a few seconds, then starts over from `next`, or quits the game.
+pub struct MessageScene {
+    msg: String,
+    background: Color,
+    next: Option<Box<dyn Scene>>,
+    start: f64,
+}
+
+impl MessageScene {
+    pub fn new(msg: &str, background: Color, next: Option<Box<dyn Scene>>) -> Self {
+        MessageScene {
+            msg: msg.to_string(),
+            background,
+            next,
+            start: 0.,
+        }
+    }
+
+    /// An error message that sends the player back to the server selector.
+    pub fn error(msg: &str) -> Self {
+        MessageScene::new(msg, RED, Some(Box::new(ServerSelectScene::new())))
+    }
+}
+
+impl Scene for MessageScene {
+    fn enter<'a>(&'a mut self, _ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        self.start = get_time();
+        Box::pin(async {})
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if get_time() - self.start > TIMEOUT {
+            return match self.next.take() {
+                Some(next) => Transition::Reset(next),
+                None => Transition::Quit,
+            };
+        }
+        show_popup(&ctx.theme, self.msg.clone());
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(self.background);
+    }
+}
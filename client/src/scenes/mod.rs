@@ -1,5 +1,250 @@
 pub mod server_select;
+pub mod connect;
 pub mod message_popup;
 pub mod inside;
 pub mod outside;
-pub mod login;
\ No newline at end of file
+pub mod login;
+pub mod journal;
+pub mod history;
+pub mod repair;
+pub mod editor;
+
+use std::{
+    future::Future,
+    net::TcpStream,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use common::{
+    assets::AssetSource,
+    conn_lib::write_flush_client,
+    map_data::Maps,
+    quest_data::{GameData, QuestCompletion},
+    quest_engine::{start_available_questlines, QuestEngine},
+    ClientState,
+};
+use macroquad::{prelude::*, ui::Skin};
+use openssl::ssl::SslStream;
+
+use crate::{
+    assets::AssetCache,
+    config::Settings,
+    content::load_content,
+    hot_reload::{can_reload, ContentWatcher},
+    scenes::{inside::MapScene, outside::OutsideScene},
+    ui::{dialog::DialogHistory, markup::DialogResources},
+};
+
+/// Everything the scenes share: loaded content, settings and the current session.
+pub struct GameContext {
+    pub config_path: String,
+    pub asset_path: String,
+    /// Where the assets at `asset_path` were last updated from.
+    pub asset_source: AssetSource,
+    /// Set by environment variables, so servers can't switch it.
+    pub asset_source_locked: bool,
+    /// Started with `--offline`, `--dev` or `--editor`, so assets are never fetched.
+    pub offline: bool,
+    /// Started with `--dev`: watches the assets to reload the content when they change.
+    pub watcher: Option<ContentWatcher>,
+    pub theme: Skin,
+    pub default_theme: Skin,
+    pub servers: Vec<String>,
+    pub settings: Settings,
+    pub maps: Maps,
+    pub game_data: GameData,
+    pub dialog_resources: DialogResources,
+    pub assets: AssetCache,
+    /// Set once the player has logged in.
+    pub session: Option<Session>,
+}
+
+impl GameContext {
+    /// The logged in session. Only scenes reached after logging in may call this.
+    pub fn session(&mut self) -> &mut Session {
+        self.session.as_mut().expect("Scene needs a logged in session")
+    }
+
+    /// Loads the content again after the assets changed, dropping everything cached from the
+    /// old ones. The current content is kept if the new one can't be loaded.
+    pub async fn reload_content(&mut self) -> Result<(), String> {
+        let content = load_content(&self.asset_path).await?;
+        self.maps = content.maps;
+        self.game_data = content.game_data;
+        self.dialog_resources = content.dialog_resources;
+        self.assets = AssetCache::new(&self.asset_path);
+        Ok(())
+    }
+
+    /// The location the current quest sends the player to, if any.
+    pub fn quest_destination(&self) -> Option<String> {
+        let session = self.session.as_ref()?;
+        match session.quest_engine.pending_completion(&self.game_data, &session.state)? {
+            QuestCompletion::ReachLocation { loc_id, .. } => Some(loc_id.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A logged in player's connection and progress.
+pub struct Session {
+    pub stream: Arc<Mutex<SslStream<TcpStream>>>,
+    pub state: ClientState,
+    pub quest_engine: QuestEngine,
+    pub dialog_history: DialogHistory,
+}
+
+impl Session {
+    /// Sends the player's state to the server to be saved.
+    pub fn save(&self) {
+        let ser = serde_json::to_string(&self.state).unwrap();
+        if let Err(e) = write_flush_client(self.stream.clone(), ser) {
+            error!("Couldn't save state: {}", e);
+        }
+    }
+}
+
+pub type SceneFuture<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// What the scene manager should do after a scene's update.
+pub enum Transition {
+    None,
+    /// Opens a scene on top of the current one.
+    Push(Box<dyn Scene>),
+    /// Closes the current scene, going back to the one below.
+    Pop,
+    /// Swaps the current scene for another.
+    Replace(Box<dyn Scene>),
+    /// Closes every scene and starts over from another.
+    Reset(Box<dyn Scene>),
+    /// Closes every scene and the game.
+    Quit,
+}
+
+/// A screen of the game, run by the `SceneManager`.
+///
+/// Each frame the manager draws the scenes from the topmost opaque one up, then updates the
+/// top scene. Updating after drawing keeps immediate mode UI and dialog on top of the scene.
+pub trait Scene {
+    /// Called when the scene is opened, before the scene it replaces exits so assets they
+    /// share stay loaded.
+    fn enter<'a>(&'a mut self, _ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        Box::pin(async {})
+    }
+
+    /// Handles input, game logic and UI windows while the scene is on top.
+    fn update(&mut self, ctx: &mut GameContext) -> Transition;
+
+    fn draw(&mut self, _ctx: &mut GameContext) {}
+
+    /// Called when the scene is closed.
+    fn exit(&mut self, _ctx: &mut GameContext) {}
+
+    /// Called on every open scene after the content was reloaded in developer mode, to pick
+    /// up the new content and assets.
+    fn reload(&mut self, _ctx: &mut GameContext) {}
+
+    /// Overlays are drawn over the scene below them instead of hiding it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// The scene for the player's current location: the location's tiled map, or the clickable
+/// campus picture when there's no walkable campus.
+pub fn world_scene(maps: &Maps, location: &str) -> Box<dyn Scene> {
+    if location.eq_ignore_ascii_case("outside") && maps.campus.is_none() {
+        Box::new(OutsideScene::new())
+    } else {
+        Box::new(MapScene::new(location))
+    }
+}
+
+/// Runs a stack of scenes, one frame at a time, until it's empty.
+#[derive(Default)]
+pub struct SceneManager {
+    stack: Vec<Box<dyn Scene>>,
+}
+
+impl SceneManager {
+    pub async fn run(&mut self, ctx: &mut GameContext, first: Box<dyn Scene>) {
+        self.push(ctx, first).await;
+        while !self.stack.is_empty() {
+            let base = self
+                .stack
+                .iter()
+                .rposition(|scene| !scene.is_overlay())
+                .unwrap_or(0);
+            for scene in &mut self.stack[base..] {
+                scene.draw(ctx);
+            }
+            let transition = match self.stack.last_mut() {
+                Some(scene) => scene.update(ctx),
+                None => break,
+            };
+            match transition {
+                Transition::None => (),
+                Transition::Push(scene) => self.push(ctx, scene).await,
+                Transition::Pop => self.pop(ctx),
+                Transition::Replace(scene) => {
+                    let old = self.stack.pop();
+                    self.push(ctx, scene).await;
+                    if let Some(mut old) = old {
+                        old.exit(ctx);
+                    }
+                }
+                Transition::Reset(scene) => {
+                    let old = std::mem::take(&mut self.stack);
+                    self.push(ctx, scene).await;
+                    for mut old in old.into_iter().rev() {
+                        old.exit(ctx);
+                    }
+                }
+                Transition::Quit => self.clear(ctx),
+            }
+            ctx.assets.process().await;
+            if ctx.watcher.as_mut().is_some_and(|watcher| watcher.poll()) {
+                self.reload(ctx).await;
+            }
+            next_frame().await
+        }
+    }
+
+    /// Reloads the content after the assets changed, keeping the player's progress.
+    async fn reload(&mut self, ctx: &mut GameContext) {
+        // Broken content is left for the author to fix rather than crashing the game
+        if !can_reload(&ctx.asset_path) {
+            return;
+        }
+        info!("Assets changed, reloading the content");
+        // Strings can only be set once, so changed translations still need a restart
+        if let Err(e) = ctx.reload_content().await {
+            error!("Couldn't reload the content: {}", e);
+            return;
+        }
+        if let Some(session) = &mut ctx.session {
+            start_available_questlines(&ctx.game_data.questlines, &mut session.state);
+        }
+        for scene in &mut self.stack {
+            scene.reload(ctx);
+        }
+    }
+
+    async fn push(&mut self, ctx: &mut GameContext, mut scene: Box<dyn Scene>) {
+        scene.enter(ctx).await;
+        self.stack.push(scene);
+    }
+
+    fn pop(&mut self, ctx: &mut GameContext) {
+        if let Some(mut scene) = self.stack.pop() {
+            scene.exit(ctx);
+        }
+    }
+
+    fn clear(&mut self, ctx: &mut GameContext) {
+        while !self.stack.is_empty() {
+            self.pop(ctx);
+        }
+    }
+}
@@ -1,107 +1,273 @@
-use std::{net::TcpStream, sync::{Arc, Mutex}};
+use common::{
+    map_data::MapLocation,
+    quest_data::QuestCompletion,
+    quest_engine::{pending_branches, start_questline, QuestEvent},
+};
+use macroquad::{prelude::*, time, ui::root_ui};
 
-use common::{conn_lib::write_flush_client, ClientState};
-use macroquad::{
-    prelude::*,
-    time,
-    ui::{root_ui, Skin},
+use crate::{
+    locale::{tr, tr_format},
+    scenes::{
+        history::HistoryScene,
+        journal::JournalScene,
+        message_popup::{draw_loading, MessageScene},
+        world_scene, GameContext, Scene, SceneFuture, Transition,
+    },
+    ui::{
+        dialog::{render_dialog, render_question},
+        quests::render_branch_choice,
+    },
 };
-use openssl::ssl::SslStream;
-
-use crate::{quest_data::GameData, ui::dialog::render_dialog};
-
-pub async fn render_outside(
-    theme: &Skin,
-    asset_path: &str,
-    outside_data: &Vec<crate::map_data::MapLocation>,
-    game_data: &GameData,
-    state: &mut ClientState,
-    stream: Arc<Mutex<SslStream<TcpStream>>>
-) -> String {
-    let asset_path = asset_path.to_string();
-    // Load Outside Map
-    let mut map_path = asset_path.clone();
-    map_path.push_str("pxArt.png");
-    let map = load_texture(&map_path)
-        .await
-        .expect("Failed to load Outside Map");
-
-    let esc_timeout = time::get_time();
-    let mut open_time = get_time();
-    let mut done_dialog = false;
-    loop {
-        // Register ESC to leave building
-        if (time::get_time() - esc_timeout) > 0.25 && is_key_pressed(KeyCode::Escape) {
-            state.location = "outside".to_string();
-            let ser = serde_json::to_string(&state).unwrap();
-            let _ = write_flush_client(stream.clone(), ser);
-            return "exit".to_string();
-        }
-        //
-        let mut exit = None;
-        root_ui().push_skin(&theme);
-        let mouse_pos = mouse_position();
-        let mouse_pos = vec2(mouse_pos.0, mouse_pos.1);
-        //
-        if is_mouse_button_pressed(MouseButton::Left) {
-            info!("{}", pixel_to_local(mouse_pos));
-        }
-        //
-        clear_background(GRAY);
-        draw_texture_ex(
-            &map,
-            0.,
-            0.,
-            GRAY,
-            DrawTextureParams {
-                dest_size: Some(Vec2::new(screen_width(), screen_height())),
-                ..Default::default()
-            },
-        );
-
-        for location in outside_data {
-            let location = draw_bounding_box(
-                local_to_pixel(vec2(location.tl_corner.x, location.tl_corner.y)),
-                local_to_pixel(vec2(location.br_corner.x, location.br_corner.y)),
-                &location.label,
-                mouse_pos.clone(),
-                &location.loc_id,
-            );
-            exit = location;
-            if exit.is_some() {
-                state.location = exit.clone().unwrap();
-                break;
+
+/// Picture of the campus with clickable buildings.
+pub const CAMPUS_PICTURE: &str = "pxArt.png";
+
+/// Clickable picture of the campus, used as the outside when there's no walkable campus map.
+pub struct OutsideScene {
+    /// Asset cache key of the picture.
+    map: String,
+    esc_timeout: f64,
+    open_time: f64,
+    done_dialog: bool,
+}
+
+impl OutsideScene {
+    pub fn new() -> Self {
+        OutsideScene {
+            map: String::new(),
+            esc_timeout: 0.,
+            open_time: 0.,
+            done_dialog: true,
+        }
+    }
+}
+
+impl Default for OutsideScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for OutsideScene {
+    fn enter<'a>(&'a mut self, ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        self.map = ctx.assets.load_texture(CAMPUS_PICTURE);
+        let session = ctx.session.as_mut().expect("Outside needs a session");
+        let state = &mut session.state;
+        self.esc_timeout = time::get_time();
+        self.open_time = get_time();
+        self.done_dialog = !(state.current_questline_id == 0 && state.current_quest_id == 0);
+        if session
+            .quest_engine
+            .handle(&ctx.game_data, state, QuestEvent::EnterLocation("outside"))
+        {
+            self.done_dialog = false;
+            state.dialog_offset += 1;
+        }
+        // Start loading where the current quest leads
+        if let Some(destination) = ctx.quest_destination() {
+            let next_maps: Vec<_> = ctx.maps.get_map(&destination).into_iter().collect();
+            ctx.assets.preload(&[], &next_maps);
+        }
+        Box::pin(async {})
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        ctx.assets.release(&self.map);
+    }
+
+    fn reload(&mut self, ctx: &mut GameContext) {
+        self.map = ctx.assets.load_texture(CAMPUS_PICTURE);
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if ctx.assets.failed(&self.map) {
+            error!("Failed to load Outside Map");
+            ctx.session().save();
+            let msg = tr_format("error.map", &["outside"]);
+            return Transition::Replace(Box::new(MessageScene::error(&msg)));
+        }
+        if ctx.assets.texture(&self.map).is_none() {
+            return Transition::None;
+        }
+        // Register ESC to quit
+        if (time::get_time() - self.esc_timeout) > 0.25 && is_key_pressed(KeyCode::Escape) {
+            ctx.session().save();
+            let closing = MessageScene::new(tr("status.closing"), RED, None);
+            return Transition::Reset(Box::new(closing));
+        }
+        // Open the quest journal
+        if is_key_pressed(KeyCode::J) {
+            return Transition::Push(Box::<JournalScene>::default());
+        }
+        // Open the dialog history
+        if is_key_pressed(KeyCode::H) {
+            return Transition::Push(Box::new(HistoryScene));
+        }
+        let game_data = &ctx.game_data;
+        let session = ctx.session.as_mut().expect("Outside needs a session");
+        let state = &mut session.state;
+        let quest_engine = &mut session.quest_engine;
+        let exit = clicked_location(&ctx.maps.outside);
+        let mut completed =
+            quest_engine.handle(game_data, state, QuestEvent::Tick(get_frame_time()));
+        if self.done_dialog {
+            if let Some(branch) = pending_branches(&game_data.questlines, state).first() {
+                root_ui().push_skin(&ctx.theme);
+                if let Some(questline) = render_branch_choice(branch) {
+                    start_questline(&game_data.questlines, state, questline);
+                    self.open_time = get_time();
+                    self.done_dialog = state.current_questline_id != questline;
+                }
+                root_ui().pop_skin();
+            }
+            if let Some(QuestCompletion::AnswerQuestion {
+                question, options, ..
+            }) = quest_engine.pending_completion(game_data, state)
+            {
+                root_ui().push_skin(&ctx.theme);
+                if let Some(choice) = render_question(question, options) {
+                    completed |= quest_engine.handle(game_data, state, QuestEvent::Answer(choice));
+                }
+                root_ui().pop_skin();
             }
         }
-        root_ui().pop_skin();
-        root_ui().pop_skin();
-        root_ui().pop_skin();
-        if state.current_questline_id == 0 && state.current_quest_id == 0 && !done_dialog {
-            let f = render_dialog(&game_data.questlines, open_time, state);
-            open_time = f.1;
-            done_dialog = f.0;
+        if completed {
+            self.done_dialog = false;
+            self.open_time = get_time();
+            state.dialog_offset += 1;
         }
-        if exit.is_some() {
-            return exit.unwrap();
+        if !self.done_dialog {
+            let f = render_dialog(
+                &game_data.questlines,
+                &ctx.dialog_resources,
+                &ctx.settings,
+                &mut session.dialog_history,
+                self.open_time,
+                state,
+            );
+            self.open_time = f.1;
+            self.done_dialog = f.0;
+        }
+        if let Some(loc_id) = exit {
+            info!("Going to {}", &loc_id);
+            state.location = loc_id;
+            state.spawn = None;
+            state.pos = ::glam::f32::vec2(0., 0.);
+            return Transition::Replace(world_scene(&ctx.maps, &state.location));
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &mut GameContext) {
+        match ctx.assets.texture(&self.map) {
+            Some(map) => draw_campus_map(map, &ctx.maps.outside),
+            None => draw_loading(&ctx.theme, ctx.assets.progress(&self.map)),
+        }
+    }
+}
+
+/// Shows the clickable campus map over the walkable campus until the player picks a
+/// building, or closes it with M or Escape.
+#[derive(Default)]
+pub struct FastTravelScene {
+    /// Asset cache key of the picture.
+    map: String,
+}
+
+impl Scene for FastTravelScene {
+    fn enter<'a>(&'a mut self, ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        self.map = ctx.assets.load_texture(CAMPUS_PICTURE);
+        Box::pin(async {})
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        ctx.assets.release(&self.map);
+    }
+
+    fn reload(&mut self, ctx: &mut GameContext) {
+        self.map = ctx.assets.load_texture(CAMPUS_PICTURE);
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if is_key_pressed(KeyCode::M)
+            || is_key_pressed(KeyCode::Escape)
+            || ctx.assets.failed(&self.map)
+        {
+            return Transition::Pop;
+        }
+        if ctx.assets.texture(&self.map).is_none() {
+            return Transition::None;
+        }
+        if let Some(loc_id) = clicked_location(&ctx.maps.outside) {
+            info!("Fast travelling to {}", &loc_id);
+            let state = &mut ctx.session.as_mut().expect("Fast travel needs a session").state;
+            state.location = loc_id;
+            state.spawn = None;
+            state.pos = ::glam::f32::vec2(0., 0.);
+            return Transition::Reset(world_scene(&ctx.maps, &state.location));
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &mut GameContext) {
+        match ctx.assets.texture(&self.map) {
+            Some(map) => draw_campus_map(map, &ctx.maps.outside),
+            None => draw_loading(&ctx.theme, ctx.assets.progress(&self.map)),
         }
-        next_frame().await
     }
 }
 
-fn draw_bounding_box(
-    pos1: Vec2,
-    pos2: Vec2,
-    label: &str,
-    mouse_pos: Vec2,
-    location: &str,
-) -> Option<String> {
-    let hover = mouse_pos.x > pos1.x
-        && mouse_pos.x < pos2.x
-        && mouse_pos.y > pos1.y
-        && mouse_pos.y < pos2.y;
-    if is_mouse_button_pressed(MouseButton::Left) && hover {
-        return Some(location.to_string());
+/// The building the player clicked on this frame.
+fn clicked_location(outside_data: &[MapLocation]) -> Option<String> {
+    if !is_mouse_button_pressed(MouseButton::Left) {
+        return None;
     }
+    let mouse_pos = mouse_position();
+    let mouse_pos = vec2(mouse_pos.0, mouse_pos.1);
+    info!("{}", pixel_to_local(mouse_pos));
+    outside_data
+        .iter()
+        .find(|location| {
+            let (pos1, pos2) = location_bounds(location);
+            is_inside(mouse_pos, pos1, pos2)
+        })
+        .map(|location| location.loc_id.clone())
+}
+
+/// Draws the campus picture with a box per building.
+fn draw_campus_map(map: &Texture2D, outside_data: &[MapLocation]) {
+    let mouse_pos = mouse_position();
+    let mouse_pos = vec2(mouse_pos.0, mouse_pos.1);
+    set_default_camera();
+    clear_background(GRAY);
+    draw_texture_ex(
+        map,
+        0.,
+        0.,
+        GRAY,
+        DrawTextureParams {
+            dest_size: Some(Vec2::new(screen_width(), screen_height())),
+            ..Default::default()
+        },
+    );
+    for location in outside_data {
+        let (pos1, pos2) = location_bounds(location);
+        draw_bounding_box(pos1, pos2, &location.label, is_inside(mouse_pos, pos1, pos2));
+    }
+}
+
+fn location_bounds(location: &MapLocation) -> (Vec2, Vec2) {
+    (
+        local_to_pixel(vec2(location.tl_corner.x, location.tl_corner.y)),
+        local_to_pixel(vec2(location.br_corner.x, location.br_corner.y)),
+    )
+}
+
+fn is_inside(pos: Vec2, pos1: Vec2, pos2: Vec2) -> bool {
+    pos.x > pos1.x && pos.x < pos2.x && pos.y > pos1.y && pos.y < pos2.y
+}
+
+fn draw_bounding_box(pos1: Vec2, pos2: Vec2, label: &str, hover: bool) {
     let rect_size = vec2(pos2.x - pos1.x, pos2.y - pos1.y);
     let rect_color = if !hover {
         Color::from_rgba(255, 0, 0, 64)
@@ -121,7 +287,6 @@ fn draw_bounding_box(
             ..Default::default()
         },
     );
-    None
 }
 
 #[allow(dead_code)]
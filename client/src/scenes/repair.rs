@@ -0,0 +1,81 @@
+use macroquad::prelude::*;
+
+use crate::{
+    asset_updater::{AssetUpdate, UpdateError},
+    integrity::{repair_all, RepairOptions},
+    locale::tr,
+    scenes::{
+        message_popup::{show_update_progress, MessageScene},
+        server_select::ServerSelectScene,
+        GameContext, Scene, SceneFuture, Transition,
+    },
+};
+
+/// Checks the assets and restores any broken files, then goes back to the server selector.
+#[derive(Default)]
+pub struct RepairScene {
+    /// The message to show once done, and whether it's an error.
+    outcome: Option<(&'static str, bool)>,
+}
+
+impl Scene for RepairScene {
+    fn enter<'a>(&'a mut self, ctx: &'a mut GameContext) -> SceneFuture<'a> {
+        Box::pin(async move {
+            // Authors edit the assets in developer mode, so their changes aren't undone
+            let options = RepairOptions {
+                offline: ctx.offline,
+                keep_modified: ctx.watcher.is_some(),
+            };
+            if options.problems(&ctx.asset_path).is_empty() {
+                info!("No problems found with the assets");
+                self.outcome = Some((tr("repair.none_found"), false));
+                return;
+            }
+            let repair = {
+                let asset_path = ctx.asset_path.clone();
+                let asset_source = ctx.asset_source.clone();
+                AssetUpdate::spawn(move |progress| {
+                    repair_all(&asset_path, &asset_source, options, progress)
+                })
+            };
+            let result = show_update_progress(
+                &ctx.theme,
+                repair,
+                tr("status.repairing_assets"),
+                tr("common.cancel"),
+            )
+            .await;
+            self.outcome = match result {
+                Ok(()) => match ctx.reload_content().await {
+                    Ok(()) => Some((tr("repair.done"), false)),
+                    Err(e) => {
+                        error!("Error loading the repaired content: {}", e);
+                        Some((tr("error.asset_repair"), true))
+                    }
+                },
+                Err(UpdateError::Cancelled) => None,
+                Err(e) => {
+                    error!("Error repairing assets: {}", e);
+                    Some((tr("error.asset_repair"), true))
+                }
+            };
+        })
+    }
+
+    fn update(&mut self, _ctx: &mut GameContext) -> Transition {
+        let next = Box::new(ServerSelectScene::new());
+        match self.outcome {
+            Some((msg, true)) => {
+                Transition::Replace(Box::new(MessageScene::new(msg, RED, Some(next))))
+            }
+            Some((msg, false)) => {
+                Transition::Replace(Box::new(MessageScene::new(msg, DARKGREEN, Some(next))))
+            }
+            None => Transition::Replace(next),
+        }
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(GRAY);
+    }
+}
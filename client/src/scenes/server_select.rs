@@ -1,19 +1,47 @@
-use std::sync::Arc;
-
 use macroquad::{
     prelude::*,
-    ui::{hash, root_ui, widgets, Skin},
+    ui::{hash, root_ui, widgets},
+};
+
+use crate::{
+    config::save_servers,
+    locale::tr,
+    scenes::{connect::ConnectScene, repair::RepairScene, GameContext, Scene, Transition},
 };
 
+/// Lists the saved servers to connect to, add and delete.
+pub struct ServerSelectScene {
+    show_add_server: bool,
+    server_to_add: String,
+    to_delete: (usize, String),
+}
+
+impl ServerSelectScene {
+    pub fn new() -> Self {
+        ServerSelectScene {
+            show_add_server: false,
+            server_to_add: String::new(),
+            to_delete: (usize::MAX, String::new()),
+        }
+    }
+}
+
+impl Default for ServerSelectScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-pub async fn run_server_selector(theme: Arc<Skin>, servers: &mut Vec<String>) -> String {
-    let mut show_add_server = false;
-    let mut server_to_add = String::new();
-    let mut to_delete: (usize, String) = (usize::MAX, String::new());
-    loop {
+impl Scene for ServerSelectScene {
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        let servers = &mut ctx.servers;
+        let server_count = servers.len();
+        let show_add_server = &mut self.show_add_server;
+        let server_to_add = &mut self.server_to_add;
+        let to_delete = &mut self.to_delete;
         let mut connect = String::new();
-        root_ui().push_skin(&theme);
-        clear_background(GRAY);
+        let mut repair = false;
+        root_ui().push_skin(&ctx.theme);
         widgets::Window::new(
             0b011100110110010101101100011001010110001101110100,
             Vec2::new(screen_width() / 10.0, screen_height() / 10.0),
@@ -22,13 +50,13 @@ pub async fn run_server_selector(theme: Arc<Skin>, servers: &mut Vec<String>) ->
         .label("Server Select")
         .titlebar(false)
         .ui(&mut root_ui(), |ui| {
-            let label_size = ui.calc_size("Select Server");
+            let label_size = ui.calc_size(tr("server_select.title"));
             ui.label(
                 Vec2::new(
                     (screen_width() / 10.0 * 8.0) / 2.0 - (label_size.x / 2.0),
                     0.0,
                 ),
-                "Select Server",
+                tr("server_select.title"),
             );
 
             for (i, server) in servers.to_vec().iter().enumerate() {
@@ -40,31 +68,38 @@ pub async fn run_server_selector(theme: Arc<Skin>, servers: &mut Vec<String>) ->
                         ui.label(Vec2::new(0.0, 25. / 2.), server);
                         if ui.button(
                             Vec2::new((screen_width()/10.*8.)-300., 4.), 
-                            "Delete"
+                            tr("server_select.delete")
                         ) {
-                            to_delete = (i, server.to_string());
+                            *to_delete = (i, server.to_string());
                         }
                         if ui.button(
                             Vec2::new((screen_width() / 10.0 * 8.0) - 200.0, 4.0),
-                            "Connect",
+                            tr("server_select.connect"),
                         ) {
                             connect.push_str(server);
                         };
                     },
                 );
             }
+            let add_size = ui.calc_size(tr("server_select.add"));
             if ui.button(
                 Vec2::new(
-                    (screen_width() / 10. * 8. / 2.) - (143.04199 / 2.),
+                    (screen_width() / 10. * 8. / 2.) - (add_size.x / 2.),
                     screen_height() / 10.0 * 8.0 - 100.,
                 ),
-                "Add Server",
+                tr("server_select.add"),
             ) {
-                show_add_server = true;
+                *show_add_server = true;
+            }
+            if ui.button(
+                Vec2::new(20., screen_height() / 10.0 * 8.0 - 100.),
+                tr("server_select.repair"),
+            ) {
+                repair = true;
             }
         });
         root_ui().move_window(0b011100110110010101101100011001010110001101110100, vec2(screen_width() / 10.0, screen_height() / 10.0));
-        if show_add_server {
+        if *show_add_server {
             widgets::Window::new(
                 0b0000101011011101,
                 Vec2::new(screen_width() / 2. - 200., screen_height() / 2. - 75.),
@@ -73,17 +108,17 @@ pub async fn run_server_selector(theme: Arc<Skin>, servers: &mut Vec<String>) ->
             .label("Add Server")
             .titlebar(false)
             .ui(&mut root_ui(), |ui| {
-                let label_size = ui.calc_size("Add Server");
+                let label_size = ui.calc_size(tr("server_select.add"));
                 ui.label(None, "");
-                ui.label(Vec2::new(200. - (label_size.x / 2.), 0.), "Add Server");
+                ui.label(Vec2::new(200. - (label_size.x / 2.), 0.), tr("server_select.add"));
                 widgets::InputText::new(hash!())
                     .size(Vec2::new(350., 35.))
-                    .ui(ui, &mut server_to_add);
+                    .ui(ui, server_to_add);
                 // ui.input_text(hash!(), "", &mut server_to_add);
-                if ui.button(Vec2::new(125., 75.), "Submit") {
+                if ui.button(Vec2::new(125., 75.), tr("common.submit")) {
                     servers.push(server_to_add.clone());
-                    server_to_add = String::new();
-                    show_add_server = false;
+                    *server_to_add = String::new();
+                    *show_add_server = false;
                 }
             });
             root_ui().move_window(0b0000101011011101, vec2(screen_width() / 2. - 200., screen_height() / 2. - 75.));
@@ -97,23 +132,36 @@ pub async fn run_server_selector(theme: Arc<Skin>, servers: &mut Vec<String>) ->
             .label("Delete Server")
             .titlebar(false)
             .ui(&mut root_ui(), |ui| {
-                ui.label(Vec2::new(87.5, 0.), "Delete Server");
+                let label_size = ui.calc_size(tr("server_select.delete_title"));
+                ui.label(Vec2::new(200. - (label_size.x / 2.), 0.), tr("server_select.delete_title"));
                 let label_size = ui.calc_size(&to_delete.1);
                 ui.label(Vec2::new(200.-(label_size.x/2.)-15., 35.), &to_delete.1);
-                if ui.button(Vec2::new(55., 75.), "Cancel") {
-                    to_delete = (usize::MAX, String::new());
+                if ui.button(Vec2::new(55., 75.), tr("common.cancel")) {
+                    *to_delete = (usize::MAX, String::new());
                 }
-                if ui.button(Vec2::new(195., 75.), "Submit") {
+                if ui.button(Vec2::new(195., 75.), tr("common.submit")) {
                     servers.remove(to_delete.0);
-                    to_delete = (usize::MAX, String::new());
+                    *to_delete = (usize::MAX, String::new());
                 }
             });
             root_ui().move_window(0b011001000110010101101100011001010111010001100101, vec2(screen_width() / 2. - 200., screen_height() / 2. - 75.));
         }
-        if connect.len() > 0 {
-            return connect;
-        }
         root_ui().pop_skin();
-        next_frame().await
+        if servers.len() != server_count {
+            if let Err(e) = save_servers(servers, &ctx.config_path) {
+                error!("Error saving server config:\n{}", e);
+            }
+        }
+        if repair {
+            return Transition::Replace(Box::new(RepairScene::default()));
+        }
+        if !connect.is_empty() {
+            return Transition::Replace(Box::new(ConnectScene::new(connect)));
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &mut GameContext) {
+        clear_background(GRAY);
     }
 }
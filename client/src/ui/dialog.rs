@@ -1,107 +1,289 @@
-use common::ClientState;
+use std::collections::VecDeque;
+
+use common::{
+    dialogue::{advance, choose, current_dialog, skip_gated_lines, start_line_quest},
+    markup::{Markup, TextStyle},
+    quest_data::{Questline, NO_QUESTLINE},
+    quest_engine::finish_questline,
+    ClientState,
+};
 use macroquad::{
     input::{is_key_pressed, KeyCode},
     math::vec2,
     time::get_time,
-    ui::{root_ui, widgets},
+    ui::{root_ui, widgets, Ui},
     window::{screen_height, screen_width},
 };
 use tracing::{error, info};
 
-use crate::quest_data::{get_next_questline_id, Questline};
+use crate::{config::Settings, ui::markup::DialogResources};
+
+/// Space between the dialog window's edges and its text.
+const TEXT_MARGIN: f32 = 40.;
+
+/// The most recent lines spoken, oldest first.
+pub struct DialogHistory {
+    lines: VecDeque<(String, String)>,
+    capacity: usize,
+}
+
+impl DialogHistory {
+    pub fn new(capacity: usize) -> Self {
+        DialogHistory {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, speaker: String, text: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back((speaker, text));
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &(String, String)> {
+        self.lines.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
 
+/// Shows the line at `dialog_offset`, typing it out from `open_time`. The first press of
+/// Space reveals the rest of the line and the second one moves on.
 pub fn render_dialog(
-    questlines: &Vec<Questline>,
+    questlines: &[Questline],
+    resources: &DialogResources,
+    settings: &Settings,
+    history: &mut DialogHistory,
     open_time: f64,
     state: &mut ClientState,
 ) -> (bool, f64) {
-    let questline = questlines
+    let dialog_data = match current_dialog(questlines, state) {
+        Some(dialog_data) => dialog_data,
+        None => {
+            error!("Cant find quest with id '{}'.", state.current_quest());
+            return (false, get_time());
+        }
+    };
+    skip_gated_lines(dialog_data, state);
+    if dialog_data.len() < (state.dialog_offset + 1) as usize {
+        // Mark current quest complete
+        state.complete_quest(state.current_quest());
+        // Move on to the next active questline
+        finish_questline(questlines, state);
+        return (state.current_questline_id == NO_QUESTLINE, get_time());
+    }
+    let current = &dialog_data[state.dialog_offset as usize];
+    let choices = current.available_choices(state);
+    let elapsed = get_time() - open_time;
+    let reveal_time = current.dialog_markup.reveal_time(state, settings.text_speed);
+    let revealed = elapsed >= reveal_time;
+    let speaker = current.speaker_markup.plain_text(state);
+    let runs = current.dialog_markup.reveal(state, f64::MAX, 0.);
+    let visible: usize = current
+        .dialog_markup
+        .reveal(state, elapsed, settings.text_speed)
         .iter()
-        .find(|ql| ql.id == state.current_questline_id);
-    if questline.is_some() {
-        let questline = questline.unwrap();
-        let quest_index = questline
-            .quests
+        .map(|(text, _)| text.chars().count())
+        .sum();
+    // Render Dialog
+    let choice_texts: Vec<String> = if revealed {
+        choices
             .iter()
-            .position(|q| q.quest_id.is_some() && q.quest_id.unwrap() == state.current_quest_id);
-        if quest_index.is_some() {
-            let quest_index = quest_index.unwrap();
-            let dialog_data = questline.quests.get(quest_index..);
-            if dialog_data.is_some() {
-                let dialog_data = dialog_data.unwrap();
-                if dialog_data.len() < (state.dialog_offset + 1) as usize {
-                    // Mark current quest complete
-                    if !state.complete_quest_ids.contains(&(state.current_questline_id+state.current_quest_id)) {
-                        state.complete_quest_ids.push(state.current_questline_id+state.current_quest_id);
-                    }
-                    // Get new questline id
-                    state.current_questline_id = get_next_questline_id(questlines, state.current_questline_id);
-                    let new_questline = questlines.iter().find(|ql|ql.id == state.current_questline_id);
-                    if new_questline.is_some() {
-                        // Set quest to 0
-                        state.current_quest_id = 0;
-                        state.dialog_offset = 0;
-                        return (false, get_time());
-                    }
-                    return (false, get_time());
-                }
-                let current = &dialog_data[state.dialog_offset as usize];
-                // Render Dialog
-                widgets::Window::new(
-                    0b011001000110100101100001011011000110111101100111,
-                    vec2(screen_width() * 0.05, screen_height() * 0.7),
-                    vec2(screen_width() * 0.9, screen_height() * 0.25),
-                )
-                .label(&current.speaker)
-                .titlebar(false)
-                .close_button(false)
-                .movable(false)
-                .ui(&mut root_ui(), |ui| {
-                    let mut speaker = current.speaker.clone();
-                    speaker = speaker.replace("<name>", &state.username);
-                    ui.label(None, &speaker);
-                    ui.label(None, "");
-                    let mut label_text = current.dialog.clone();
-                    label_text = label_text.replace("<name>", &state.username);
-                    let mut current_width = 0.;
-                    let sliced_text = label_text.split_whitespace();
-                    let mut wrapped_text = String::new();
-                    for word in sliced_text {
-                        let mut word = String::from(word);
-                        word.push(' ');
-                        let text_size = ui.calc_size(&word);
-                        current_width += text_size.x;
-                        if current_width > screen_width() * 0.8 {
-                            current_width = text_size.x;
-                            wrapped_text.push('\n');
-                        }
-                        wrapped_text.push_str(&word);
-                    }
-                    for line in wrapped_text.split('\n') {
-                        ui.label(None, line);
-                    }
-                });
-                root_ui().move_window(
-                    0b011001000110100101100001011011000110111101100111,
-                    vec2(screen_width() * 0.9, screen_height() * 0.25),
-                );
-                //
-                if is_key_pressed(KeyCode::Space) && get_time() - open_time > 1f64 {
-                    state.dialog_offset+=1;
-                    if current.quest_id.is_some() {
-                        state.current_quest_id = current.quest_id.unwrap();
-                        state.dialog_offset = 0;
-                        info!("Quest updated: {}", state.current_quest_id);
-                        return (true, get_time());
-                    }
-                    return (false, get_time());
-                } else {
-                    return (false, open_time);
+            .map(|choice| choice.text_markup.plain_text(state))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let picked = draw_dialog_window(
+        resources,
+        &current.dialog_markup,
+        &speaker,
+        &runs,
+        visible,
+        &choice_texts,
+    );
+    let pressed = is_key_pressed(KeyCode::Space);
+    if !revealed {
+        // Skip the rest of the typing
+        if pressed {
+            return (false, get_time() - reveal_time);
+        }
+        return (false, open_time);
+    }
+    let dialog = current.dialog_markup.plain_text(state);
+    if !choices.is_empty() {
+        let picked = picked.or(pressed_number().filter(|i| *i < choices.len()));
+        let choice = match picked {
+            Some(i) => choices[i],
+            None => return (false, open_time),
+        };
+        history.push(speaker, dialog);
+        history.push(state.username.clone(), choice.text_markup.plain_text(state));
+        let line = state.dialog_offset;
+        choose(questlines, dialog_data, state, choice);
+        if let Some(quest_id) = current.quest_id {
+            return start_quest(state, quest_id, line);
+        }
+        return (false, get_time());
+    }
+    if pressed {
+        history.push(speaker, dialog);
+        let line = state.dialog_offset;
+        advance(dialog_data, state);
+        if let Some(quest_id) = current.quest_id {
+            return start_quest(state, quest_id, line);
+        }
+        return (false, get_time());
+    }
+    (false, open_time)
+}
+
+/// Draws the dialog window: `speaker`, the first `visible` characters of `runs` and a
+/// numbered button per choice, with the line's portrait above it. Returns the index of the
+/// choice clicked this frame.
+pub fn draw_dialog_window(
+    resources: &DialogResources,
+    markup: &Markup,
+    speaker: &str,
+    runs: &[(String, TextStyle)],
+    visible: usize,
+    choices: &[String],
+) -> Option<usize> {
+    let mut picked = None;
+    let dialog_pos = vec2(screen_width() * 0.05, screen_height() * 0.7);
+    let dialog_size = vec2(screen_width() * 0.9, screen_height() * 0.25);
+    resources.draw_portrait(markup, dialog_pos);
+    widgets::Window::new(
+        0b011001000110100101100001011011000110111101100111,
+        dialog_pos,
+        dialog_size,
+    )
+    .label(speaker)
+    .titlebar(false)
+    .close_button(false)
+    .movable(false)
+    .ui(&mut root_ui(), |ui| {
+        ui.label(None, speaker);
+        ui.label(None, "");
+        resources.render_runs(ui, runs, visible, dialog_size.x - TEXT_MARGIN);
+        for (i, choice) in choices.iter().enumerate() {
+            let text = format!("{}. {}", i + 1, choice);
+            if ui.button(None, text.as_str()) {
+                picked = Some(i);
+            }
+        }
+    });
+    root_ui().move_window(
+        0b011001000110100101100001011011000110111101100111,
+        dialog_pos,
+    );
+    picked
+}
+
+fn start_quest(state: &mut ClientState, quest_id: u16, line: u16) -> (bool, f64) {
+    start_line_quest(state, quest_id, line);
+    info!("Quest updated: {}", state.current_quest_id);
+    (true, get_time())
+}
+
+/// The zero based index of the number key (1-9) pressed this frame.
+fn pressed_number() -> Option<usize> {
+    const KEYS: [KeyCode; 9] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    KEYS.iter().position(|key| is_key_pressed(*key))
+}
+
+/// Shows a quest question with one button per option and returns the option clicked this frame.
+pub fn render_question(question: &str, options: &[String]) -> Option<usize> {
+    let mut choice = None;
+    let window_size = vec2(screen_width() * 0.5, 100. + (options.len() as f32 * 45.));
+    let window_pos = vec2(
+        (screen_width() - window_size.x) / 2.,
+        (screen_height() - window_size.y) / 2.,
+    );
+    widgets::Window::new(
+        0b011000010110111001110011011101110110010101110010,
+        window_pos,
+        window_size,
+    )
+    .label("Question")
+    .titlebar(false)
+    .close_button(false)
+    .movable(false)
+    .ui(&mut root_ui(), |ui| {
+        ui.label(None, question);
+        ui.label(None, "");
+        for (i, option) in options.iter().enumerate() {
+            if ui.button(None, option.as_str()) {
+                choice = Some(i);
+            }
+        }
+    });
+    root_ui().move_window(
+        0b011000010110111001110011011101110110010101110010,
+        window_pos,
+    );
+    choice
+}
+
+/// Splits `text` into lines no wider than `max_width` when drawn with `ui`'s label style.
+/// Words wider than a whole line are broken up.
+pub fn wrap_text(ui: &mut Ui, text: &str, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut current_width = 0.;
+        for word in paragraph.split_whitespace() {
+            for piece in break_word(ui, &format!("{} ", word), max_width) {
+                let piece_width = ui.calc_size(&piece).x;
+                if current_width + piece_width > max_width && current_width > 0. {
+                    lines.push(line.trim_end().to_string());
+                    line.clear();
+                    current_width = 0.;
                 }
+                current_width += piece_width;
+                line.push_str(&piece);
             }
         }
-    } else {
-        error!("Cant find quest with id '{}'.", &state.current_quest_id);
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+/// Splits `word` into pieces no wider than `max_width`. Trailing spaces don't count towards
+/// the width.
+pub fn break_word(ui: &mut Ui, word: &str, max_width: f32) -> Vec<String> {
+    if ui.calc_size(word.trim_end()).x <= max_width {
+        return vec![word.to_string()];
+    }
+    let mut pieces = Vec::new();
+    let mut piece = String::new();
+    for c in word.chars() {
+        piece.push(c);
+        if !c.is_whitespace() && ui.calc_size(&piece).x > max_width && piece.chars().count() > 1 {
+            piece.pop();
+            pieces.push(std::mem::take(&mut piece));
+            piece.push(c);
+        }
+    }
+    if !piece.is_empty() {
+        pieces.push(piece);
     }
-    (false, get_time())
+    pieces
 }
@@ -0,0 +1,158 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use common::{
+    markup::{Markup, TextStyle},
+    quest_data::GameData,
+};
+use macroquad::{
+    prelude::*,
+    ui::{root_ui, Skin, Ui},
+};
+
+use crate::ui::dialog::break_word;
+
+/// Color used for `[em]` text that has no color of its own.
+const EMPHASIS_COLOR: [u8; 4] = [30, 60, 150, 255];
+
+/// Portraits and label skins used to draw dialogue markup.
+pub struct DialogResources {
+    portraits: HashMap<String, Texture2D>,
+    skins: RefCell<HashMap<[u8; 4], Skin>>,
+}
+
+impl DialogResources {
+    /// Loads every portrait referenced by the questlines from `<assets>/portraits/`.
+    pub async fn load(asset_path: &str, game_data: &GameData) -> Self {
+        let mut portraits = HashMap::new();
+        let names = game_data
+            .questlines
+            .iter()
+            .flat_map(|ql| ql.quests.iter())
+            .filter_map(|q| q.dialog_markup.portrait.clone());
+        for name in names {
+            if portraits.contains_key(&name) {
+                continue;
+            }
+            let mut portrait_path = String::from(asset_path);
+            portrait_path.push_str("portraits/");
+            portrait_path.push_str(&name);
+            match load_texture(&portrait_path).await {
+                Ok(texture) => {
+                    texture.set_filter(FilterMode::Nearest);
+                    portraits.insert(name, texture);
+                }
+                Err(e) => warn!("Couldn't load portrait '{}': {}", portrait_path, e),
+            }
+        }
+        DialogResources {
+            portraits,
+            skins: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn skin(&self, color: [u8; 4]) -> Skin {
+        self.skins
+            .borrow_mut()
+            .entry(color)
+            .or_insert_with(|| {
+                let label_style = root_ui()
+                    .style_builder()
+                    .text_color(Color::from_rgba(color[0], color[1], color[2], color[3]))
+                    .build();
+                Skin {
+                    label_style,
+                    ..root_ui().default_skin()
+                }
+            })
+            .clone()
+    }
+
+    /// Draws the line's portrait above the top left corner of `dialog_pos`.
+    pub fn draw_portrait(&self, markup: &Markup, dialog_pos: Vec2) {
+        let texture = match markup.portrait.as_ref().and_then(|p| self.portraits.get(p)) {
+            Some(texture) => texture,
+            None => return,
+        };
+        let size = screen_height() * 0.2;
+        draw_texture_ex(
+            texture,
+            dialog_pos.x,
+            dialog_pos.y - size,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(size, size)),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Draws the first `visible` characters of styled text runs, wrapping them to
+    /// `max_width`. Wrapping uses the whole text so words don't jump lines while typed out.
+    pub fn render_runs(
+        &self,
+        ui: &mut Ui,
+        runs: &[(String, TextStyle)],
+        visible: usize,
+        max_width: f32,
+    ) {
+        let mut remaining = visible;
+        for line in wrap_runs(ui, runs, max_width) {
+            if remaining == 0 {
+                break;
+            }
+            for (i, (text, style)) in line.iter().enumerate() {
+                if remaining == 0 {
+                    break;
+                }
+                let length = text.chars().count();
+                let text: String = text.chars().take(remaining).collect();
+                remaining -= length.min(remaining);
+                if i > 0 {
+                    ui.same_line(0.);
+                }
+                let color = match (style.color, style.emphasis) {
+                    (Some(color), _) => Some(color),
+                    (None, true) => Some(EMPHASIS_COLOR),
+                    (None, false) => None,
+                };
+                match color {
+                    Some(color) => {
+                        ui.push_skin(&self.skin(color));
+                        ui.label(None, &text);
+                        ui.pop_skin();
+                    }
+                    None => ui.label(None, &text),
+                }
+            }
+        }
+    }
+}
+
+/// Splits styled runs into lines no wider than `max_width`, breaking between words and
+/// within words wider than a line.
+fn wrap_runs(
+    ui: &mut Ui,
+    runs: &[(String, TextStyle)],
+    max_width: f32,
+) -> Vec<Vec<(String, TextStyle)>> {
+    let mut lines: Vec<Vec<(String, TextStyle)>> = vec![Vec::new()];
+    let mut current_width = 0.;
+    for (text, style) in runs {
+        for word in text.split_inclusive(' ') {
+            for piece in break_word(ui, word, max_width) {
+                let piece_width = ui.calc_size(&piece).x;
+                if current_width + piece_width > max_width && current_width > 0. {
+                    lines.push(Vec::new());
+                    current_width = 0.;
+                }
+                current_width += piece_width;
+                let line = lines.last_mut().unwrap();
+                match line.last_mut() {
+                    Some((last, last_style)) if last_style == style => last.push_str(&piece),
+                    _ => line.push((piece, *style)),
+                }
+            }
+        }
+    }
+    lines
+}
@@ -1,2 +1,4 @@
 pub mod theme;
-pub mod dialog;
\ No newline at end of file
+pub mod dialog;
+pub mod quests;
+pub mod markup;
\ No newline at end of file
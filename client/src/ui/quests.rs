@@ -0,0 +1,122 @@
+use common::{
+    quest_data::{QuestRef, Questline},
+    ClientState,
+};
+use macroquad::{
+    math::{vec2, Vec2},
+    ui::{root_ui, widgets},
+    window::{screen_height, screen_width},
+};
+
+use crate::locale::{tr, tr_format};
+
+/// Lists every active quest, marking the current one. Returns the questline the player
+/// chose to track instead.
+pub fn render_quest_status(questlines: &[Questline], state: &ClientState) -> Option<u16> {
+    let mut focus = None;
+    let current = state.current_quest();
+    let names: Vec<(QuestRef, String)> = state
+        .active_quests()
+        .into_iter()
+        .map(|quest| {
+            let name = quest_name(questlines, quest)
+                .unwrap_or(tr_format("quests.unnamed_quest", &[&quest.to_string()]));
+            if quest == current {
+                (quest, format!("> {}", name))
+            } else {
+                (quest, name)
+            }
+        })
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+
+    let button_size = root_ui().calc_size(tr("quests.track"));
+    let mut width: f32 = 0.;
+    let mut height = 0.;
+    for (_, name) in &names {
+        let Vec2 { x, y } = root_ui().calc_size(name);
+        width = width.max(x + button_size.x);
+        height += y.max(button_size.y);
+    }
+    let margin = screen_width() * 0.025;
+    let window_width = width + (margin * 2.);
+    let window_height = height + (margin * 2.);
+
+    root_ui().move_window(
+        1010,
+        Vec2::new(screen_width() - window_width - margin, margin),
+    );
+    widgets::Window::new(
+        1010,
+        Vec2::new(screen_width() - window_width - margin, margin),
+        Vec2::new(window_width, window_height),
+    )
+    .label(tr("quests.title"))
+    .titlebar(true)
+    .close_button(false)
+    .ui(&mut root_ui(), |ui| {
+        for (quest, name) in &names {
+            ui.label(None, name);
+            if *quest != current {
+                ui.same_line(0.);
+                if ui.button(None, tr("quests.track")) {
+                    focus = Some(quest.questline);
+                }
+            }
+        }
+    });
+    focus
+}
+
+/// Lets the player pick one questline out of a branch. Returns the chosen questline.
+pub fn render_branch_choice(branch: &[&Questline]) -> Option<u16> {
+    let mut choice = None;
+    let window_size = vec2(screen_width() * 0.5, 100. + (branch.len() as f32 * 45.));
+    let window_pos = vec2(
+        (screen_width() - window_size.x) / 2.,
+        (screen_height() - window_size.y) / 2.,
+    );
+    widgets::Window::new(
+        0b011000100111001001100001011011100110001101101000,
+        window_pos,
+        window_size,
+    )
+    .label("Choose a Path")
+    .titlebar(false)
+    .close_button(false)
+    .movable(false)
+    .ui(&mut root_ui(), |ui| {
+        ui.label(None, tr("quests.choose_path"));
+        ui.label(None, "");
+        for questline in branch {
+            let label = questline
+                .name
+                .clone()
+                .unwrap_or(tr_format(
+                    "quests.unnamed_questline",
+                    &[&questline.id.to_string()],
+                ));
+            if ui.button(None, label.as_str()) {
+                choice = Some(questline.id);
+            }
+        }
+    });
+    root_ui().move_window(
+        0b011000100111001001100001011011100110001101101000,
+        window_pos,
+    );
+    choice
+}
+
+fn quest_name(questlines: &[Questline], quest: QuestRef) -> Option<String> {
+    questlines
+        .iter()
+        .find(|ql| ql.id == quest.questline)?
+        .quests
+        .iter()
+        .find(|q| q.quest_id == Some(quest.quest))?
+        .quest_name
+        .clone()
+}
@@ -0,0 +1,439 @@
+use std::{
+    env, fs, io,
+    path::{Component, Path},
+};
+
+use openssl::{
+    error::ErrorStack,
+    pkey::PKey,
+    sha::sha256,
+    sign::{Signer, Verifier},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{map_data::Maps, quest_data::GameData};
+
+/// Repository the official assets are fetched from.
+pub const DEFAULT_ASSET_URL: &str =
+    "https://github.com/DarkCoder28/CIS4000_Capstone-Spring2024-ASSETS.git";
+pub const DEFAULT_ASSET_BRANCH: &str = "master";
+
+pub const ASSET_URL_VAR: &str = "GV_ASSET_URL";
+pub const ASSET_BRANCH_VAR: &str = "GV_ASSET_BRANCH";
+pub const ASSET_TAG_VAR: &str = "GV_ASSET_TAG";
+pub const ASSET_COMMIT_VAR: &str = "GV_ASSET_COMMIT";
+pub const ASSET_KIND_VAR: &str = "GV_ASSET_KIND";
+
+/// Files the game reads when they're there.
+pub const OPTIONAL_FILES: [&str; 1] = ["objects.json"];
+
+/// Stands for the game server's host in the URL of a pack it serves, since the server
+/// can't know the name players reach it by.
+pub const SERVER_HOST: &str = "{server}";
+
+/// Manifest at the root of an asset pack, and its detached signature.
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const SIGNATURE_FILE: &str = "manifest.json.sig";
+
+/// Files holding the game's maps and quests. The game can't start without them, and a
+/// player's progress only makes sense against the server's versions.
+pub const CONTENT_FILES: [&str; 2] = ["map_data.json", "questlines.json"];
+
+/// How assets are delivered.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    /// A git repository, checked out at a branch, tag or commit.
+    #[default]
+    Git,
+    /// Plain files listed in a signed manifest, downloaded over HTTP(S).
+    Pack,
+}
+
+/// Where the client's assets come from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct AssetSource {
+    pub kind: AssetKind,
+    /// URL of the repository, or the path to a local (possibly bare) one. For packs, the URL
+    /// of the directory holding the manifest.
+    pub url: String,
+    /// Branch to follow when no tag is set.
+    pub branch: String,
+    /// Tag to check out instead of following a branch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Commit to check out, pinning the assets to an exact revision of the branch or tag.
+    /// Packs must have been built from this commit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl Default for AssetSource {
+    fn default() -> Self {
+        AssetSource {
+            kind: AssetKind::Git,
+            url: DEFAULT_ASSET_URL.to_string(),
+            branch: DEFAULT_ASSET_BRANCH.to_string(),
+            tag: None,
+            commit: None,
+        }
+    }
+}
+
+impl AssetSource {
+    /// The source set by `GV_ASSET_KIND` (`git` or `pack`), `GV_ASSET_URL`,
+    /// `GV_ASSET_BRANCH`, `GV_ASSET_TAG` and `GV_ASSET_COMMIT`, or `None` when none of them
+    /// are set. Unset parts come from `base`.
+    pub fn from_env(base: &AssetSource) -> Option<Self> {
+        let kind = env::var(ASSET_KIND_VAR).ok();
+        let url = env::var(ASSET_URL_VAR).ok();
+        let branch = env::var(ASSET_BRANCH_VAR).ok();
+        let tag = env::var(ASSET_TAG_VAR).ok();
+        let commit = env::var(ASSET_COMMIT_VAR).ok();
+        if kind.is_none() && url.is_none() && branch.is_none() && tag.is_none() && commit.is_none()
+        {
+            return None;
+        }
+        let mut source = base.clone();
+        if let Some(kind) = kind {
+            match serde_json::from_value(serde_json::Value::String(kind.to_lowercase())) {
+                Ok(kind) => source.kind = kind,
+                Err(_) => warn!("Unknown asset kind '{}', expected git or pack", kind),
+            }
+        }
+        if let Some(url) = url {
+            source.url = url;
+        }
+        if let Some(branch) = branch {
+            // A branch picked explicitly wins over the base's tag
+            source.branch = branch;
+            source.tag = None;
+            source.commit = None;
+        }
+        if tag.is_some() {
+            source.tag = tag;
+            source.commit = None;
+        }
+        if commit.is_some() {
+            source.commit = commit;
+        }
+        Some(source)
+    }
+
+    /// The branch or tag checked out, for logs and messages.
+    pub fn reference(&self) -> String {
+        let reference = match (self.kind, &self.tag) {
+            (AssetKind::Pack, _) => String::from("signed pack"),
+            (AssetKind::Git, Some(tag)) => format!("tag {}", tag),
+            (AssetKind::Git, None) => format!("branch {}", self.branch),
+        };
+        match &self.commit {
+            Some(commit) => format!("commit {} of {}", commit, reference),
+            None => reference,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("the manifest's signature doesn't match")]
+    BadSignature,
+    #[error("{0}")]
+    Crypto(#[from] ErrorStack),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unsafe path '{0}' in the manifest")]
+    UnsafePath(String),
+}
+
+/// A file of an asset pack.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Path relative to the pack, with `/` separators.
+    pub path: String,
+    /// Hex encoded SHA-256 of the file.
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// Lists every file of an asset pack, so clients only download the ones they're missing.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetManifest {
+    /// Commit the pack was built from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl AssetManifest {
+    /// Lists the files under `dir`, skipping hidden ones and the manifest itself.
+    pub fn build(dir: &Path, version: Option<String>) -> io::Result<Self> {
+        let mut files = Vec::new();
+        add_files(dir, "", &mut files)?;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(AssetManifest { version, files })
+    }
+
+    /// Checks `manifest` was signed by the owner of `public_key` (PEM, Ed25519) before
+    /// parsing it.
+    pub fn verify(
+        manifest: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<Self, ManifestError> {
+        let key = PKey::public_key_from_pem(public_key)?;
+        let mut verifier = Verifier::new_without_digest(&key)?;
+        if !verifier.verify_oneshot(signature, manifest)? {
+            return Err(ManifestError::BadSignature);
+        }
+        let manifest: AssetManifest = serde_json::from_slice(manifest)?;
+        if let Some(entry) = manifest
+            .files
+            .iter()
+            .find(|entry| !is_safe_path(&entry.path))
+        {
+            return Err(ManifestError::UnsafePath(entry.path.clone()));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Signs a manifest's bytes with `private_key` (PEM, Ed25519).
+pub fn sign_manifest(manifest: &[u8], private_key: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let key = PKey::private_key_from_pem(private_key)?;
+    let mut signer = Signer::new_without_digest(&key)?;
+    signer.sign_oneshot_to_vec(manifest)
+}
+
+/// Every file the content refers to, relative to the assets: the content files, the Tiled
+/// maps of walkable locations with their tilesets, and dialog portraits.
+pub fn referenced_files(maps: &Maps, game_data: &GameData) -> Vec<String> {
+    let mut files: Vec<String> = CONTENT_FILES
+        .iter()
+        .chain(OPTIONAL_FILES.iter())
+        .map(|file| file.to_string())
+        .collect();
+    for map in maps.walkable_maps() {
+        files.push(format!("maps/{}", map.tilemap_path));
+        for (_, tileset) in &map.tilemap_texture_mappings {
+            files.push(format!("maps/{}", tileset));
+        }
+    }
+    let portraits = game_data
+        .questlines
+        .iter()
+        .flat_map(|ql| ql.quests.iter())
+        .filter_map(|q| q.dialog_markup.portrait.as_ref());
+    for portrait in portraits {
+        files.push(format!("portraits/{}", portrait));
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Hex encoded SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    sha256(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Whether `path` stays inside the directory it's relative to.
+pub fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// `path` as it goes in a URL: every `/` separated segment percent-encoded, so file names
+/// with spaces, `#`, `?` or non-ASCII characters can be downloaded.
+pub fn encode_url_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            segment
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                        (b as char).to_string()
+                    }
+                    _ => format!("%{:02X}", b),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Decodes the percent escapes in a URL path. `None` when an escape is malformed or the
+/// result isn't UTF-8. The result still has to be checked with `is_safe_path`.
+pub fn decode_url_path(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn add_files(dir: &Path, prefix: &str, files: &mut Vec<ManifestEntry>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.')
+            || (prefix.is_empty() && name == MANIFEST_FILE)
+            || (prefix.is_empty() && name == SIGNATURE_FILE)
+        {
+            continue;
+        }
+        let path = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            add_files(&entry.path(), &format!("{}/", path), files)?;
+        } else {
+            let data = fs::read(entry.path())?;
+            files.push(ManifestEntry {
+                path,
+                sha256: sha256_hex(&data),
+                size: data.len() as u64,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A new Ed25519 key pair as PEM, private key first.
+    fn key_pair() -> (Vec<u8>, Vec<u8>) {
+        let key = PKey::generate_ed25519().unwrap();
+        (
+            key.private_key_to_pem_pkcs8().unwrap(),
+            key.public_key_to_pem().unwrap(),
+        )
+    }
+
+    fn manifest_json(path: &str) -> Vec<u8> {
+        serde_json::to_vec(&AssetManifest {
+            version: Some(String::from("abc123")),
+            files: vec![ManifestEntry {
+                path: path.to_string(),
+                sha256: sha256_hex(b"{}"),
+                size: 2,
+            }],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn verifies_signed_manifests() {
+        let (private, public) = key_pair();
+        let manifest = manifest_json("maps/hall.json");
+        let signature = sign_manifest(&manifest, &private).unwrap();
+        let verified = AssetManifest::verify(&manifest, &signature, &public).unwrap();
+        assert_eq!(verified.version.as_deref(), Some("abc123"));
+        assert_eq!(verified.files[0].path, "maps/hall.json");
+    }
+
+    #[test]
+    fn rejects_tampered_manifests_and_other_keys() {
+        let (private, public) = key_pair();
+        let (_, other_public) = key_pair();
+        let manifest = manifest_json("maps/hall.json");
+        let signature = sign_manifest(&manifest, &private).unwrap();
+
+        let tampered = manifest_json("maps/hal.json");
+        assert!(matches!(
+            AssetManifest::verify(&tampered, &signature, &public),
+            Err(ManifestError::BadSignature)
+        ));
+        assert!(matches!(
+            AssetManifest::verify(&manifest, &signature, &other_public),
+            Err(ManifestError::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_signed_manifests_with_unsafe_paths() {
+        let (private, public) = key_pair();
+        let manifest = manifest_json("../outside.txt");
+        let signature = sign_manifest(&manifest, &private).unwrap();
+        assert!(matches!(
+            AssetManifest::verify(&manifest, &signature, &public),
+            Err(ManifestError::UnsafePath(path)) if path == "../outside.txt"
+        ));
+    }
+
+    #[test]
+    fn safe_paths_stay_inside_the_assets() {
+        for path in ["questlines.json", "maps/hall.json", "portraits/a b.png"] {
+            assert!(is_safe_path(path), "{}", path);
+        }
+        for path in [
+            "",
+            "..",
+            "maps/../../etc/passwd",
+            "/etc/passwd",
+            "./maps/hall.json",
+        ] {
+            assert!(!is_safe_path(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn url_paths_round_trip() {
+        let path = "maps/Tile Set #1?.png";
+        assert_eq!(encode_url_path(path), "maps/Tile%20Set%20%231%3F.png");
+        for path in [path, "portraits/ñ.png", "questlines.json"] {
+            assert_eq!(
+                decode_url_path(&encode_url_path(path)).as_deref(),
+                Some(path)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_broken_or_escaping_url_paths() {
+        for path in ["a%2", "a%zz", "a%+1", "%FF"] {
+            assert!(decode_url_path(path).is_none(), "{}", path);
+        }
+        let escaping = decode_url_path("maps/%2E%2E/%2E%2E/etc/passwd").unwrap();
+        assert!(!is_safe_path(&escaping));
+    }
+
+    #[test]
+    fn builds_manifests_without_hidden_files_or_the_manifest() {
+        let dir = env::temp_dir().join(format!("gv-manifest-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("maps")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join("maps/hall.json"), "{}").unwrap();
+        fs::write(dir.join(".git/HEAD"), "ref").unwrap();
+        fs::write(dir.join(MANIFEST_FILE), "{}").unwrap();
+
+        let manifest = AssetManifest::build(&dir, None).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            manifest.files,
+            vec![ManifestEntry {
+                path: String::from("maps/hall.json"),
+                sha256: sha256_hex(b"{}"),
+                size: 2,
+            }]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Checks the game's content for broken references and other mistakes.
+//!
+//! Usage: `gv-lint <assets dir>`
+//!
+//! Reads `map_data.json`, `questlines.json`, `objects.json`, the string tables in `locales/`
+//! and the Tiled maps of every walkable location. Prints one line per problem and exits with
+//! 1 if any is fatal or an error, so it can run in CI. Warnings alone don't fail.
+
+use std::{env, path::Path, process::ExitCode};
+
+use common::lint::{lint_dir, Severity};
+
+fn main() -> ExitCode {
+    let assets = match env::args().nth(1) {
+        Some(assets) => assets,
+        None => {
+            eprintln!("Usage: gv-lint <assets dir>");
+            return ExitCode::from(2);
+        }
+    };
+    match lint_dir(Path::new(&assets)) {
+        Ok(issues) => {
+            for issue in &issues {
+                println!("{}", issue);
+            }
+            let errors = issues
+                .iter()
+                .filter(|issue| issue.severity != Severity::Warning)
+                .count();
+            println!("{} errors, {} warnings", errors, issues.len() - errors);
+            if errors > 0 {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
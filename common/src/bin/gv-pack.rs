@@ -0,0 +1,63 @@
+//! Turns a directory of assets into a signed asset pack.
+//!
+//! Usage: `gv-pack <assets dir> <private key> [version]`
+//!
+//! Writes `manifest.json`, listing every file with its SHA-256, and `manifest.json.sig`, its
+//! signature with the Ed25519 private key (PEM), into the assets dir. The version is usually
+//! the commit the assets were built from. Serve the directory as is over HTTP(S), or from the
+//! game server with `GV_PACK_DIR`.
+//!
+//! A key pair can be made with:
+//!
+//! ```text
+//! openssl genpkey -algorithm ed25519 -out pack_key.pem
+//! openssl pkey -in pack_key.pem -pubout -out pack_key.pub.pem
+//! ```
+//!
+//! Clients only accept packs signed with the key in `GV_PACK_PUBLIC_KEY` when they were built.
+//! `client/build.sh` takes it from `certs/pack_key.pub.pem` when the variable isn't set.
+//! Clients built without it still update from git, but refuse pack sources.
+
+use std::{env, fs, path::Path, process::ExitCode};
+
+use common::assets::{sign_manifest, AssetManifest, MANIFEST_FILE, SIGNATURE_FILE};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (assets, key) = match (args.first(), args.get(1)) {
+        (Some(assets), Some(key)) => (Path::new(assets), Path::new(key)),
+        _ => {
+            eprintln!("Usage: gv-pack <assets dir> <private key> [version]");
+            return ExitCode::from(2);
+        }
+    };
+    match run(assets, key, args.get(2).cloned()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(assets: &Path, key: &Path, version: Option<String>) -> Result<(), String> {
+    let key = fs::read(key).map_err(|e| format!("{}: {}", key.display(), e))?;
+    let manifest = AssetManifest::build(assets, version)
+        .map_err(|e| format!("{}: {}", assets.display(), e))?;
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    let signature =
+        sign_manifest(&manifest_json, &key).map_err(|e| format!("Couldn't sign: {}", e))?;
+
+    let manifest_path = assets.join(MANIFEST_FILE);
+    fs::write(&manifest_path, &manifest_json)
+        .map_err(|e| format!("{}: {}", manifest_path.display(), e))?;
+    let signature_path = assets.join(SIGNATURE_FILE);
+    fs::write(&signature_path, &signature)
+        .map_err(|e| format!("{}: {}", signature_path.display(), e))?;
+    println!(
+        "Packed {} files into {}",
+        manifest.files.len(),
+        manifest_path.display()
+    );
+    Ok(())
+}
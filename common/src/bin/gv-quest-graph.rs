@@ -0,0 +1,70 @@
+//! Draws the questlines as a graph.
+//!
+//! Usage: `gv-quest-graph <assets dir> [dot|mermaid]`
+//!
+//! Reads `questlines.json`, `objects.json` and the objects placed in the Tiled maps, and
+//! prints a Graphviz (the default) or Mermaid graph of the questlines, their quests and the
+//! objects, locations, items and flags they refer to. Render it with e.g.
+//! `gv-quest-graph assets | dot -Tsvg > quests.svg`.
+
+use std::{env, fs, path::Path, process::ExitCode};
+
+use common::{
+    map_data::Maps,
+    map_objects::MapObjects,
+    quest_data::GameData,
+    quest_graph::{GraphFormat, QuestGraph},
+};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (assets, format) = match args.as_slice() {
+        [assets] => (assets, Ok(GraphFormat::Dot)),
+        [assets, format] => (assets, format.parse()),
+        _ => {
+            eprintln!("Usage: gv-quest-graph <assets dir> [dot|mermaid]");
+            return ExitCode::from(2);
+        }
+    };
+    let result = format.and_then(|format| {
+        let game_data = read_game_data(Path::new(assets))?;
+        Ok(QuestGraph::build(&game_data).render(format))
+    });
+    match result {
+        Ok(graph) => {
+            print!("{}", graph);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn read_game_data(assets: &Path) -> Result<GameData, String> {
+    let questlines = read(&assets.join("questlines.json"))?;
+    let objects = read(&assets.join("objects.json")).unwrap_or_else(|_| String::from("[]"));
+    let mut game_data = GameData::from_json(&questlines, &objects).map_err(|e| e.to_string())?;
+
+    // Objects placed in the maps are only needed to show where they are, so maps that can't
+    // be read are left out
+    if let Ok(map_data) = read(&assets.join("map_data.json")) {
+        if let Ok(maps) = Maps::from_json(&map_data) {
+            for map in maps.walkable_maps() {
+                let path = assets.join("maps").join(&map.tilemap_path);
+                let objects = read(&path).and_then(|json| {
+                    MapObjects::from_tiled_json(&map.loc_id, &json).map_err(|e| e.to_string())
+                });
+                if let Ok(objects) = objects {
+                    game_data.add_map_objects(&objects);
+                }
+            }
+        }
+    }
+    Ok(game_data)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,109 @@
+//! Reports keys a locale hasn't translated yet.
+//!
+//! Usage: `gv-untranslated <assets dir> [locale...]`
+//!
+//! Checks every locale in `<assets dir>/locales/` except English when no locales are given.
+//! Exits with 1 if any key is missing. Lines without a `node` can't be translated, so they
+//! are listed separately as needing one rather than counted as missing.
+
+use std::{env, fs, path::Path, process::ExitCode};
+
+use common::{
+    locale::{parse_table, translatable_keys, DEFAULT_LOCALE},
+    map_data::Maps,
+    quest_data::Questline,
+};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let assets = match args.first() {
+        Some(assets) => Path::new(assets),
+        None => {
+            eprintln!("Usage: gv-untranslated <assets dir> [locale...]");
+            return ExitCode::from(2);
+        }
+    };
+    match run(assets, &args[1..]) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Returns `true` if every locale is fully translated.
+fn run(assets: &Path, locales: &[String]) -> Result<bool, String> {
+    let questlines = read(&assets.join("questlines.json"))?;
+    let questlines: Vec<Questline> =
+        serde_json::from_str(&questlines).map_err(|e| format!("questlines.json: {}", e))?;
+    let maps = read(&assets.join("map_data.json"))?;
+    let maps = Maps::from_json(&maps).map_err(|e| format!("map_data.json: {}", e))?;
+    let keys = translatable_keys(&questlines, &maps);
+    for questline in &questlines {
+        for (i, line) in questline.quests.iter().enumerate() {
+            if line.node.is_none() {
+                println!(
+                    "line {} of questline {} needs a node to be translated",
+                    i, questline.id
+                );
+            }
+        }
+    }
+
+    let locale_dir = assets.join("locales");
+    let locales = if locales.is_empty() {
+        let mut found = Vec::new();
+        let entries =
+            fs::read_dir(&locale_dir).map_err(|e| format!("{}: {}", locale_dir.display(), e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(locale) = path.file_stem().and_then(|s| s.to_str()) {
+                    if locale != DEFAULT_LOCALE {
+                        found.push(locale.to_string());
+                    }
+                }
+            }
+        }
+        found.sort();
+        found
+    } else {
+        locales.to_vec()
+    };
+
+    let mut complete = true;
+    for locale in &locales {
+        let path = locale_dir.join(format!("{}.json", locale));
+        let table = parse_table(&read(&path)?).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let missing: Vec<_> = keys
+            .iter()
+            .filter(|(key, _)| !table.contains_key(*key))
+            .collect();
+        let mut unknown: Vec<_> = table
+            .keys()
+            .filter(|key| !keys.contains_key(*key))
+            .collect();
+        unknown.sort();
+
+        println!(
+            "{}: {} of {} keys untranslated",
+            locale,
+            missing.len(),
+            keys.len()
+        );
+        for (key, english) in &missing {
+            println!("  missing {} = {:?}", key, english);
+        }
+        for key in &unknown {
+            println!("  unknown {}", key);
+        }
+        complete &= missing.is_empty();
+    }
+    Ok(complete)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
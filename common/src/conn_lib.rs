@@ -1,46 +1,91 @@
-use std::{io::{Write, Read}, net::TcpStream, sync::{Arc, Mutex}};
+use std::{io::{Error, ErrorKind, Write, Read}, net::TcpStream, sync::{Arc, Mutex}};
 
 use openssl::ssl::SslStream;
 
+/// Largest message either side accepts. Messages are sent as a 4 byte big-endian length
+/// followed by that many bytes of UTF-8.
+pub const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
 
+fn write_message(writer: &mut impl Write, msg: &str) -> Result<(), Error> {
+    if msg.len() > MAX_MESSAGE_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("message of {} bytes is over the {} byte limit", msg.len(), MAX_MESSAGE_LEN),
+        ));
+    }
+    let mut msg_data = Vec::with_capacity(4 + msg.len());
+    msg_data.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+    msg_data.extend_from_slice(msg.as_bytes());
+    writer.write_all(&msg_data)?;
+    writer.flush()
+}
 
-pub fn write_flush_client(stream: Arc<Mutex<SslStream<TcpStream>>>, msg: String) -> Result<(), std::io::Error> {
-    // Pad Message to 1024 bytes
-    let mut msg_data = [0u8;1024];
-    for (i,b) in msg.bytes().into_iter().enumerate() {
-        msg_data[i] = b;
+fn read_message(reader: &mut impl Read) -> Result<String, Error> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("message of {} bytes is over the {} byte limit", len, MAX_MESSAGE_LEN),
+        ));
     }
-    // Write Message
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+pub fn write_flush_client(stream: Arc<Mutex<SslStream<TcpStream>>>, msg: String) -> Result<(), std::io::Error> {
     let mut writer = stream.lock().expect("Failed to lock stream");
-    let s = writer.write_all(&msg_data);
-    writer.flush()?;
-    s
+    write_message(&mut *writer, &msg)
 }
 
 pub fn read_stream_client(stream: Arc<Mutex<SslStream<TcpStream>>>) -> Result<String, std::io::Error> {
     let mut reader = stream.lock().expect("Failed to lock stream");
-    let mut buf = [0u8; 1024];
-    reader.read_exact(&mut buf)?;
-    let msg = String::from_utf8(buf.to_vec()).unwrap();
-    Ok(String::from(msg.trim_matches(char::from(0))))
+    read_message(&mut *reader)
 }
 
 
 pub async fn write_flush(stream: Arc<Mutex<SslStream<TcpStream>>>, msg: String) -> Result<(), std::io::Error> {
-    // Pad Message to 1024 bytes
-    let mut msg_data = [0u8;1024];
-    for (i,b) in msg.bytes().into_iter().enumerate() {
-        msg_data[i] = b;
-    }
-    // Write Message
-    let s = stream.lock().expect("Failed to lock stream").write_all(&msg_data);
-    stream.lock().expect("Failed to lock stream").flush()?;
-    s
+    write_message(&mut *stream.lock().expect("Failed to lock stream"), &msg)
 }
 
 pub async fn read_stream(stream: Arc<Mutex<SslStream<TcpStream>>>) -> Result<String, std::io::Error> {
-    let mut buf = [0u8; 1024];
-    stream.lock().expect("Failed to lock stream").read_exact(&mut buf)?;
-    let msg = String::from_utf8(buf.to_vec()).unwrap();
-    Ok(String::from(msg.trim_matches(char::from(0))))
-}
\ No newline at end of file
+    read_message(&mut *stream.lock().expect("Failed to lock stream"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let long = "x".repeat(5000);
+        let mut buf = Vec::new();
+        write_message(&mut buf, "hello").unwrap();
+        write_message(&mut buf, &long).unwrap();
+        write_message(&mut buf, "").unwrap();
+        let mut reader = Cursor::new(buf);
+        assert_eq!(read_message(&mut reader).unwrap(), "hello");
+        assert_eq!(read_message(&mut reader).unwrap(), long);
+        assert_eq!(read_message(&mut reader).unwrap(), "");
+    }
+
+    #[test]
+    fn rejects_oversized_messages() {
+        let mut reader = Cursor::new(u32::MAX.to_be_bytes().to_vec());
+        let err = read_message(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reports_truncated_messages() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, "hello").unwrap();
+        buf.truncate(6);
+        let err = read_message(&mut Cursor::new(buf)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,145 @@
+use tracing::error;
+
+use crate::{
+    quest_data::{DialogChoice, Quest, QuestRef, Questline},
+    quest_engine::start_available_questlines,
+    ClientState,
+};
+
+/// The dialogue lines from the player's current quest onwards. `dialog_offset` indexes
+/// into this slice.
+pub fn current_dialog<'a>(questlines: &'a [Questline], state: &ClientState) -> Option<&'a [Quest]> {
+    let questline = questlines
+        .iter()
+        .find(|ql| ql.id == state.current_questline_id)?;
+    let quest_index = questline
+        .quests
+        .iter()
+        .position(|q| q.quest_id == Some(state.current_quest_id))?;
+    questline.quests.get(quest_index..)
+}
+
+/// Moves `dialog_offset` past any lines whose conditions the player doesn't meet.
+pub fn skip_gated_lines(lines: &[Quest], state: &mut ClientState) {
+    while let Some(line) = lines.get(state.dialog_offset as usize) {
+        if line.conditions_met(state) {
+            break;
+        }
+        state.dialog_offset += 1;
+    }
+}
+
+/// Continues the dialogue at the line named `node`. Only lines from the current quest
+/// onwards can be jumped to; returns `false` if there is no such line.
+pub fn jump_to_node(lines: &[Quest], state: &mut ClientState, node: &str) -> bool {
+    match lines.iter().position(|l| l.node.as_deref() == Some(node)) {
+        Some(index) => {
+            state.dialog_offset = index as u16;
+            true
+        }
+        None => {
+            error!(
+                "Dialogue node '{}' not found after quest {}",
+                node,
+                state.current_quest()
+            );
+            false
+        }
+    }
+}
+
+/// Moves on from the line at `dialog_offset`, following its `goto` if it has one.
+pub fn advance(lines: &[Quest], state: &mut ClientState) {
+    let goto = lines
+        .get(state.dialog_offset as usize)
+        .and_then(|line| line.goto.as_deref());
+    match goto {
+        Some(node) if jump_to_node(lines, state, node) => (),
+        _ => state.dialog_offset += 1,
+    }
+}
+
+/// Applies the player's pick of `choice` on the line at `dialog_offset`.
+pub fn choose(
+    questlines: &[Questline],
+    lines: &[Quest],
+    state: &mut ClientState,
+    choice: &DialogChoice,
+) {
+    for flag in &choice.set_flags {
+        state.set_flag(flag);
+    }
+    match &choice.goto {
+        Some(node) if jump_to_node(lines, state, node) => (),
+        _ => state.dialog_offset += 1,
+    }
+    // Flags may unlock questlines
+    start_available_questlines(questlines, state);
+}
+
+/// Starts `quest_id`, given on the line at offset `line`, after `choose` or `advance` moved
+/// the player on from it. The offset is rebased on the quest's first line and left one
+/// short of where the player moved to, so completing the quest carries on there. Jumps
+/// back to lines before the quest can't be kept and continue after it instead.
+pub fn start_line_quest(state: &mut ClientState, quest_id: u16, line: u16) {
+    let next = state.dialog_offset;
+    if next <= line {
+        error!(
+            "Dialogue can't jump back before quest {} once it starts",
+            QuestRef::new(state.current_questline_id, quest_id)
+        );
+    }
+    state.current_quest_id = quest_id;
+    state.dialog_offset = next.saturating_sub(line + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUESTLINES: &str = r#"[{"id": 0, "quests": [
+        {"speaker": "Bob", "dialog": "Hi", "quest_id": 0},
+        {"speaker": "Bob", "dialog": "Will you help?", "quest_id": 1, "choices": [
+            {"text": "Yes", "goto": "thanks"},
+            {"text": "No"}
+        ]},
+        {"speaker": "Bob", "dialog": "Shame."},
+        {"speaker": "Bob", "dialog": "Thanks!", "node": "thanks"}
+    ]}]"#;
+
+    /// Picks `choice` on quest 1's line, starts the quest and completes it the way the
+    /// scenes do, returning the line the dialogue continues at.
+    fn line_after_quest(choice: usize) -> String {
+        let questlines: Vec<Questline> = serde_json::from_str(QUESTLINES).unwrap();
+        let mut state = ClientState::new("tester");
+        state.dialog_offset = 1;
+        let lines = current_dialog(&questlines, &state).unwrap();
+        let line = &lines[1];
+        choose(&questlines, lines, &mut state, &line.choices[choice]);
+        start_line_quest(&mut state, line.quest_id.unwrap(), 1);
+        assert_eq!(state.current_quest_id, 1);
+
+        state.dialog_offset += 1;
+        let lines = current_dialog(&questlines, &state).unwrap();
+        lines[state.dialog_offset as usize].dialog.clone()
+    }
+
+    #[test]
+    fn quest_started_by_a_choice_keeps_its_goto() {
+        assert_eq!(line_after_quest(0), "Thanks!");
+    }
+
+    #[test]
+    fn quest_started_by_a_choice_continues_after_the_line() {
+        assert_eq!(line_after_quest(1), "Shame.");
+    }
+
+    #[test]
+    fn jump_back_before_the_quest_continues_after_it() {
+        let mut state = ClientState::new("tester");
+        state.dialog_offset = 0;
+        start_line_quest(&mut state, 1, 2);
+        assert_eq!(state.current_quest_id, 1);
+        assert_eq!(state.dialog_offset, 0);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    locale::Localization,
+    quest_data::{GameData, Quest, QuestRef, Questline},
+    quest_engine::available_questlines,
+    ClientState,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuestStatus {
+    Active,
+    Completed,
+    Upcoming,
+}
+
+#[derive(Clone, Debug)]
+pub struct JournalQuest {
+    pub quest: QuestRef,
+    pub status: QuestStatus,
+    pub name: String,
+    /// The description as the player sees it, with its markup resolved.
+    pub description: String,
+    /// `(speaker, dialog)` lines the player has already heard for this quest.
+    pub history: Vec<(String, String)>,
+    pub hint: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct JournalQuestline {
+    pub id: u16,
+    pub name: String,
+    pub quests: Vec<JournalQuest>,
+}
+
+/// Builds the journal for every questline the player has started or can start.
+pub fn build_journal(
+    game_data: &GameData,
+    state: &ClientState,
+    strings: &Localization,
+) -> Vec<JournalQuestline> {
+    let available: Vec<u16> = available_questlines(&game_data.questlines, state)
+        .iter()
+        .map(|ql| ql.id)
+        .collect();
+    game_data
+        .questlines
+        .iter()
+        .filter(|ql| state.is_questline_started(ql.id) || available.contains(&ql.id))
+        .map(|ql| JournalQuestline {
+            id: ql.id,
+            name: ql
+                .name
+                .clone()
+                .unwrap_or(strings.format("quests.unnamed_questline", &[&ql.id.to_string()])),
+            quests: journal_quests(ql, state, strings),
+        })
+        .collect()
+}
+
+fn journal_quests(
+    questline: &Questline,
+    state: &ClientState,
+    strings: &Localization,
+) -> Vec<JournalQuest> {
+    let active = state.active_quests();
+    let mut quests: Vec<JournalQuest> = Vec::new();
+    for entry in &questline.quests {
+        let quest_id = match entry.quest_id {
+            Some(quest_id) => quest_id,
+            None => {
+                // Lines without an id are spoken once the quest before them is completed
+                if let Some(quest) = quests.last_mut() {
+                    if quest.status == QuestStatus::Completed {
+                        quest.history.push(spoken_line(entry, state));
+                    }
+                }
+                continue;
+            }
+        };
+        let quest = QuestRef::new(questline.id, quest_id);
+        let status = if state.is_quest_complete(quest) {
+            QuestStatus::Completed
+        } else if active.contains(&quest) {
+            QuestStatus::Active
+        } else {
+            QuestStatus::Upcoming
+        };
+        let history = if status == QuestStatus::Upcoming {
+            Vec::new()
+        } else {
+            vec![spoken_line(entry, state)]
+        };
+        quests.push(JournalQuest {
+            quest,
+            status,
+            name: entry
+                .quest_name
+                .clone()
+                .unwrap_or(strings.format("quests.unnamed_quest", &[&quest.to_string()])),
+            description: match &entry.description {
+                Some(_) => entry.description_markup.plain_text(state),
+                None => entry.dialog_markup.plain_text(state),
+            },
+            history,
+            hint: entry
+                .hint
+                .clone()
+                .or(entry.completion.as_ref().map(|c| c.hint(strings))),
+        });
+    }
+    quests
+}
+
+fn spoken_line(entry: &Quest, state: &ClientState) -> (String, String) {
+    (
+        entry.speaker_markup.plain_text(state),
+        entry.dialog_markup.plain_text(state),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUESTLINES: &str = r#"[
+        {"id": 0, "name": "Intro", "quests": [
+            {"speaker": "Bob", "dialog": "Hi {name}", "quest_id": 0, "quest_name": "Meet Bob",
+             "description": "Say [em]hi[/em] to <name>",
+             "completion": {"type": "talk_to", "who": "bob"}},
+            {"speaker": "Bob", "dialog": "Welcome"},
+            {"speaker": "Bob", "dialog": "Find [color=#ff0000]it[/color]", "quest_id": 1,
+             "hint": "Look in the library",
+             "completion": {"type": "collect_item", "items": ["key"]}}
+        ]},
+        {"id": 1, "prerequisites": [{"type": "flag", "flag": "never"}], "quests": [
+            {"speaker": "Amy", "dialog": "Hidden", "quest_id": 0}
+        ]},
+        {"id": 2, "prerequisites": [], "quests": [
+            {"speaker": "Cat", "dialog": "Later", "quest_id": 0,
+             "completion": {"type": "timer", "seconds": 3}}
+        ]}
+    ]"#;
+
+    fn journal() -> Vec<JournalQuestline> {
+        let game_data = GameData::from_json(QUESTLINES, "[]").unwrap();
+        let mut state = ClientState::new("tester");
+        state.complete_quest(QuestRef::new(0, 0));
+        state.current_quest_id = 1;
+        build_journal(&game_data, &state, &Localization::english())
+    }
+
+    #[test]
+    fn shows_progress_with_markup_resolved() {
+        let journal = journal();
+        let quests = &journal[0].quests;
+        assert_eq!(journal[0].name, "Intro");
+        assert_eq!(quests.len(), 2);
+
+        assert_eq!(quests[0].status, QuestStatus::Completed);
+        assert_eq!(quests[0].name, "Meet Bob");
+        assert_eq!(quests[0].description, "Say hi to tester");
+        assert_eq!(
+            quests[0].history,
+            vec![
+                ("Bob".to_string(), "Hi tester".to_string()),
+                ("Bob".to_string(), "Welcome".to_string())
+            ]
+        );
+
+        assert_eq!(quests[1].status, QuestStatus::Active);
+        assert_eq!(quests[1].description, "Find it");
+        assert_eq!(quests[1].hint.as_deref(), Some("Look in the library"));
+    }
+
+    #[test]
+    fn lists_questlines_the_player_can_start() {
+        let journal = journal();
+        let ids: Vec<u16> = journal.iter().map(|ql| ql.id).collect();
+        assert_eq!(ids, vec![0, 2]);
+
+        let strings = Localization::english();
+        let upcoming = &journal[1];
+        assert_eq!(
+            upcoming.name,
+            strings.format("quests.unnamed_questline", &["2"])
+        );
+        assert_eq!(upcoming.quests[0].status, QuestStatus::Upcoming);
+        assert!(upcoming.quests[0].history.is_empty());
+        assert_eq!(upcoming.quests[0].hint.as_deref(), Some("Wait 3 seconds"));
+    }
+}
@@ -1,8 +1,24 @@
+pub mod assets;
 pub mod conn_lib;
+pub mod dialogue;
+pub mod journal;
+pub mod lint;
+pub mod locale;
+pub mod map_data;
+pub mod map_objects;
+pub mod markup;
+pub mod quest_data;
+pub mod quest_engine;
+pub mod quest_graph;
 
+use assets::AssetSource;
 use glam::{f32::Vec2, vec2};
+use quest_data::QuestRef;
 use serde::{Serialize, Deserialize};
 
+/// Version of the `ClientState` save format written by this build.
+pub const SAVE_VERSION: u16 = 1;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ClientState {
     pub username: String,
@@ -10,10 +26,35 @@ pub struct ClientState {
     pub pos: Vec2,
     pub speed: Vec2,
     pub location: String,
+    /// Spawn point to place the player at when `location` next loads, set by map exits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn: Option<String>,
     pub current_quest_id: u16,
     pub current_questline_id: u16,
     pub dialog_offset: u16,
-    pub complete_quest_ids: Vec<u16>,
+    #[serde(rename = "complete_quests", default)]
+    pub complete_quest_ids: Vec<QuestRef>,
+    /// Completions from saves made before `QuestRef`, stored as `questline + quest` sums.
+    /// Resolved against the questlines by `quest_data::migrate_save`.
+    #[serde(rename = "complete_quest_ids", default, skip_serializing_if = "Vec::is_empty")]
+    pub legacy_complete_quest_ids: Vec<u16>,
+    #[serde(default)]
+    pub visited_locations: Vec<String>,
+    #[serde(default)]
+    pub inventory: Vec<String>,
+    /// In-progress questlines other than the current one, each at the quest it is on.
+    #[serde(default)]
+    pub active_questlines: Vec<QuestRef>,
+    /// Dialog offsets of the questlines in `active_questlines`, as `(questline, offset)`,
+    /// so their dialog picks up where it was left when they are focused again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub active_dialog_offsets: Vec<(u16, u16)>,
+    #[serde(default)]
+    pub complete_questlines: Vec<u16>,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub save_version: u16,
 }
 
 impl ClientState {
@@ -23,11 +64,57 @@ impl ClientState {
             pos: vec2(0., 0.),
             speed: vec2(0. ,0.),
             location: String::from("outside"),
+            spawn: None,
             authenticated: true,
             current_quest_id: 0,
             current_questline_id: 0,
             dialog_offset: 0,
             complete_quest_ids: Vec::new(),
+            legacy_complete_quest_ids: Vec::new(),
+            visited_locations: Vec::new(),
+            inventory: Vec::new(),
+            active_questlines: Vec::new(),
+            active_dialog_offsets: Vec::new(),
+            complete_questlines: Vec::new(),
+            flags: Vec::new(),
+            save_version: SAVE_VERSION,
+        }
+    }
+
+    pub fn current_quest(&self) -> QuestRef {
+        QuestRef::new(self.current_questline_id, self.current_quest_id)
+    }
+
+    pub fn is_quest_complete(&self, quest: QuestRef) -> bool {
+        self.complete_quest_ids.contains(&quest)
+    }
+
+    /// The current quest followed by the quests of every other active questline.
+    pub fn active_quests(&self) -> Vec<QuestRef> {
+        let mut quests = Vec::new();
+        if self.current_questline_id != quest_data::NO_QUESTLINE {
+            quests.push(self.current_quest());
+        }
+        quests.extend(self.active_questlines.iter().copied());
+        quests
+    }
+
+    pub fn is_questline_started(&self, questline: u16) -> bool {
+        self.current_questline_id == questline
+            || self.complete_questlines.contains(&questline)
+            || self.active_questlines.iter().any(|q| q.questline == questline)
+    }
+
+    pub fn set_flag(&mut self, flag: &str) {
+        if !self.flags.iter().any(|f| f == flag) {
+            self.flags.push(flag.to_string());
+        }
+    }
+
+    /// Records `quest` as complete, ignoring quests that are already recorded.
+    pub fn complete_quest(&mut self, quest: QuestRef) {
+        if !self.is_quest_complete(quest) {
+            self.complete_quest_ids.push(quest);
         }
     }
 }
@@ -41,7 +128,7 @@ pub struct UserStore {
 
 impl UserStore {
     pub fn new(username: &str, pass_hash: u64) -> Self {
-        UserStore { username: String::from(username), pass_hash: pass_hash, state: ClientState::new(username) }
+        UserStore { username: String::from(username), pass_hash, state: ClientState::new(username) }
     }
 }
 
@@ -49,4 +136,13 @@ impl UserStore {
 pub struct ClientAuth {
     pub username: String,
     pub pass_hash: u64,
-}
\ No newline at end of file
+}
+
+/// First message the server sends a new connection, before the client logs in.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ServerHello {
+    /// Assets the server's content is built from, if it advertises them. Clients check out
+    /// the exact commit when one is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets: Option<AssetSource>,
+}
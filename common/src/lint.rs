@@ -0,0 +1,765 @@
+//! Checks content for mistakes that would otherwise only show up while playing: references
+//! to locations, objects, quests and nodes that don't exist, duplicate ids, quests that
+//! can't be completed, questlines that can never start and translations that would break
+//! when lines are reordered.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt, fs,
+    path::Path,
+};
+
+use crate::{
+    locale::{parse_table, StringTable},
+    map_data::Maps,
+    map_objects::MapObjects,
+    quest_data::{
+        Condition, GameData, ObjectQuestRef, Quest, QuestCompletion, QuestRef, Questline,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// Crashes the game when it loads the content or enters the map.
+    Fatal,
+    /// Breaks the game or leaves the player stuck.
+    Error,
+    /// Probably a mistake, but the game copes with it.
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// File the problem is in, relative to the assets.
+    pub file: String,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Fatal => "fatal",
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", self.file, severity, self.message)
+    }
+}
+
+/// A walkable location's Tiled map, read with `MapObjects::from_tiled_json`.
+pub struct LoadedMap {
+    pub loc_id: String,
+    pub objects: MapObjects,
+}
+
+/// Everything the checks look at. `game_data` should include the objects placed in `maps`.
+pub struct LintContent<'a> {
+    pub maps: &'a Maps,
+    pub game_data: &'a GameData,
+    pub tiled_maps: &'a [LoadedMap],
+    /// Whether a file exists, relative to the assets.
+    pub file_exists: &'a dyn Fn(&str) -> bool,
+    /// The string tables in `locales/`, by locale.
+    pub locales: &'a [(String, StringTable)],
+}
+
+const MAP_DATA: &str = "map_data.json";
+const QUESTLINES: &str = "questlines.json";
+const OBJECTS: &str = "objects.json";
+
+struct Linter {
+    issues: Vec<Issue>,
+}
+
+impl Linter {
+    fn fatal(&mut self, file: &str, message: String) {
+        self.issues.push(Issue {
+            severity: Severity::Fatal,
+            file: file.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, file: &str, message: String) {
+        self.issues.push(Issue {
+            severity: Severity::Error,
+            file: file.to_string(),
+            message,
+        });
+    }
+
+    fn warning(&mut self, file: &str, message: String) {
+        self.issues.push(Issue {
+            severity: Severity::Warning,
+            file: file.to_string(),
+            message,
+        });
+    }
+}
+
+/// Reads the content in `assets` the way the client does and checks it. Fails when the
+/// content files can't be read at all.
+pub fn lint_dir(assets: &Path) -> Result<Vec<Issue>, String> {
+    let read =
+        |file: &str| fs::read_to_string(assets.join(file)).map_err(|e| format!("{}: {}", file, e));
+    let maps = Maps::from_json(&read(MAP_DATA)?).map_err(|e| format!("{}: {}", MAP_DATA, e))?;
+    let objects = read(OBJECTS).unwrap_or_else(|_| String::from("[]"));
+    let mut game_data =
+        GameData::from_json(&read(QUESTLINES)?, &objects).map_err(|e| e.to_string())?;
+
+    // Maps that can't be read are reported and left out of the other checks
+    let mut issues = Vec::new();
+    let mut tiled_maps = Vec::new();
+    for map in maps.walkable_maps() {
+        let file = format!("maps/{}", map.tilemap_path);
+        let Ok(json) = fs::read_to_string(assets.join(&file)) else {
+            continue;
+        };
+        match MapObjects::from_tiled_json(&map.loc_id, &json) {
+            Ok(objects) => {
+                game_data.add_map_objects(&objects);
+                tiled_maps.push(LoadedMap {
+                    loc_id: map.loc_id.clone(),
+                    objects,
+                });
+            }
+            Err(e) => issues.push(Issue {
+                severity: Severity::Fatal,
+                file,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    // Locales are optional; ones that can't be read are reported and left out
+    let mut locales = Vec::new();
+    let mut files: Vec<_> = fs::read_dir(assets.join("locales"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    for path in files {
+        let Some(locale) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let file = format!("locales/{}.json", locale);
+        let table = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| parse_table(&json).map_err(|e| e.to_string()));
+        match table {
+            Ok(table) => locales.push((locale.to_string(), table)),
+            Err(e) => issues.push(Issue {
+                severity: Severity::Error,
+                file,
+                message: e,
+            }),
+        }
+    }
+
+    let file_exists = |path: &str| assets.join(path).is_file();
+    issues.extend(lint(&LintContent {
+        maps: &maps,
+        game_data: &game_data,
+        tiled_maps: &tiled_maps,
+        file_exists: &file_exists,
+        locales: &locales,
+    }));
+    Ok(issues)
+}
+
+/// Runs every check over `content`.
+pub fn lint(content: &LintContent) -> Vec<Issue> {
+    let mut linter = Linter { issues: Vec::new() };
+    check_maps(&mut linter, content);
+    check_objects(&mut linter, content);
+    check_questlines(&mut linter, content);
+    check_reachability(&mut linter, &content.game_data.questlines);
+    check_translations(&mut linter, content);
+    linter.issues
+}
+
+fn is_location(maps: &Maps, loc_id: &str) -> bool {
+    loc_id.eq_ignore_ascii_case("outside") || maps.get_inside(loc_id).is_some()
+}
+
+fn check_maps(linter: &mut Linter, content: &LintContent) {
+    let maps = content.maps;
+    let mut seen = HashSet::new();
+    for map in &maps.insides {
+        if !seen.insert(map.loc_id.as_str()) {
+            linter.error(MAP_DATA, format!("duplicate location '{}'", map.loc_id));
+        }
+    }
+    let mut seen = HashSet::new();
+    for location in &maps.outside {
+        if !seen.insert(location.loc_id.as_str()) {
+            linter.warning(
+                MAP_DATA,
+                format!("'{}' is on the campus map twice", location.loc_id),
+            );
+        }
+        if maps.get_inside(&location.loc_id).is_none() {
+            linter.error(
+                MAP_DATA,
+                format!(
+                    "building '{}' on the campus map has no map to enter",
+                    location.loc_id
+                ),
+            );
+        }
+    }
+
+    // Textures and Tiled files
+    for map in maps.walkable_maps() {
+        let tilemap = format!("maps/{}", map.tilemap_path);
+        if !(content.file_exists)(&tilemap) {
+            linter.fatal(
+                MAP_DATA,
+                format!("map of '{}' is missing: {}", map.loc_id, tilemap),
+            );
+        }
+        for (name, tileset) in &map.tilemap_texture_mappings {
+            let texture = format!("maps/{}", tileset);
+            if !(content.file_exists)(&texture) {
+                linter.fatal(
+                    MAP_DATA,
+                    format!(
+                        "tileset '{}' of '{}' is missing: {}",
+                        name, map.loc_id, texture
+                    ),
+                );
+            }
+        }
+    }
+
+    // Exits have to lead somewhere, and to a spawn point that's there
+    for tiled in content.tiled_maps {
+        let file = maps
+            .get_map(&tiled.loc_id)
+            .map(|map| format!("maps/{}", map.tilemap_path))
+            .unwrap_or_else(|| MAP_DATA.to_string());
+        for exit in &tiled.objects.exits {
+            if !is_location(maps, &exit.target) {
+                linter.error(
+                    &file,
+                    format!("exit leads to unknown location '{}'", exit.target),
+                );
+                continue;
+            }
+            let (Some(spawn), Some(target)) = (
+                &exit.spawn,
+                content
+                    .tiled_maps
+                    .iter()
+                    .find(|map| map.loc_id.eq_ignore_ascii_case(&exit.target)),
+            ) else {
+                continue;
+            };
+            if !target.objects.spawns.iter().any(|s| &s.name == spawn) {
+                linter.warning(
+                    &file,
+                    format!(
+                        "exit to '{}' uses spawn point '{}', which isn't there",
+                        exit.target, spawn
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn check_objects(linter: &mut Linter, content: &LintContent) {
+    let maps = content.maps;
+    let mut seen = HashSet::new();
+    for object in &content.game_data.object_locations {
+        if !seen.insert(object.object_id.to_lowercase()) {
+            linter.warning(
+                OBJECTS,
+                format!("object id '{}' is used more than once", object.object_id),
+            );
+        }
+        let Some(map) = maps.get_map(&object.loc_id) else {
+            linter.error(
+                OBJECTS,
+                format!(
+                    "object '{}' is in unknown location '{}'",
+                    object.object_id, object.loc_id
+                ),
+            );
+            continue;
+        };
+        let has_tileset = map
+            .tilemap_texture_mappings
+            .iter()
+            .any(|(name, _)| *name == object.sprite.sprite_map);
+        if !has_tileset {
+            linter.error(
+                OBJECTS,
+                format!(
+                    "object '{}' uses tileset '{}', which '{}' doesn't have",
+                    object.object_id, object.sprite.sprite_map, object.loc_id
+                ),
+            );
+        }
+        for quest in object.relevant_quest_ids.iter().flatten() {
+            let known = match quest {
+                ObjectQuestRef::Quest(quest) => {
+                    find_quest(&content.game_data.questlines, *quest).is_some()
+                }
+                ObjectQuestRef::LegacySum(_) => false,
+            };
+            if !known {
+                linter.warning(
+                    OBJECTS,
+                    format!(
+                        "object '{}' is relevant to unknown quest {}",
+                        object.object_id, quest
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn find_quest(questlines: &[Questline], quest: QuestRef) -> Option<&Quest> {
+    questlines
+        .iter()
+        .find(|ql| ql.id == quest.questline)?
+        .quests
+        .iter()
+        .find(|q| q.quest_id == Some(quest.quest))
+}
+
+/// Flags any quest or dialogue choice of `questlines` sets.
+fn flags_set<'a>(questlines: impl Iterator<Item = &'a Questline>) -> HashSet<&'a str> {
+    questlines
+        .flat_map(|ql| ql.quests.iter())
+        .flat_map(|q| {
+            q.set_flags
+                .iter()
+                .chain(q.choices.iter().flat_map(|c| c.set_flags.iter()))
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+fn check_condition(
+    linter: &mut Linter,
+    content: &LintContent,
+    flags: &HashSet<&str>,
+    condition: &Condition,
+    what: &str,
+) {
+    let questlines = &content.game_data.questlines;
+    match condition {
+        Condition::QuestComplete { quest } => {
+            if find_quest(questlines, *quest).is_none() {
+                linter.error(
+                    QUESTLINES,
+                    format!("{} needs unknown quest {}", what, quest),
+                );
+            }
+        }
+        Condition::QuestlineComplete { questline } => {
+            if !questlines.iter().any(|ql| ql.id == *questline) {
+                linter.error(
+                    QUESTLINES,
+                    format!("{} needs unknown questline {}", what, questline),
+                );
+            }
+        }
+        Condition::Flag { flag } | Condition::NotFlag { flag } => {
+            if !flags.contains(flag.as_str()) {
+                linter.warning(
+                    QUESTLINES,
+                    format!("{} checks flag '{}', which nothing sets", what, flag),
+                );
+            }
+        }
+        Condition::Visited { loc_id } => {
+            if !is_location(content.maps, loc_id) {
+                linter.error(
+                    QUESTLINES,
+                    format!("{} needs a visit to unknown location '{}'", what, loc_id),
+                );
+            }
+        }
+    }
+}
+
+fn check_questlines(linter: &mut Linter, content: &LintContent) {
+    let game_data = content.game_data;
+    let maps = content.maps;
+    let flags = flags_set(game_data.questlines.iter());
+    let items: HashSet<&str> = game_data
+        .object_locations
+        .iter()
+        .filter_map(|obj| obj.item.as_deref())
+        .collect();
+    let has_object = |who: &str| {
+        game_data
+            .object_locations
+            .iter()
+            .any(|obj| obj.object_id.eq_ignore_ascii_case(who))
+    };
+
+    let mut seen = HashSet::new();
+    for questline in &game_data.questlines {
+        if !seen.insert(questline.id) {
+            linter.error(
+                QUESTLINES,
+                format!("duplicate questline id {}", questline.id),
+            );
+        }
+        for condition in questline.prerequisites.iter().flatten() {
+            let what = format!("questline {}", questline.id);
+            check_condition(linter, content, &flags, condition, &what);
+        }
+
+        let mut quest_ids = HashSet::new();
+        let mut nodes = HashSet::new();
+        for quest in &questline.quests {
+            if let Some(node) = &quest.node {
+                if !nodes.insert(node.as_str()) {
+                    linter.error(
+                        QUESTLINES,
+                        format!("questline {} has node '{}' twice", questline.id, node),
+                    );
+                }
+            }
+        }
+        for (line, quest) in questline.quests.iter().enumerate() {
+            let what = match quest.quest_id {
+                Some(id) => format!("quest {}", QuestRef::new(questline.id, id)),
+                None => format!("line {} of questline {}", line, questline.id),
+            };
+            if let Some(id) = quest.quest_id {
+                if !quest_ids.insert(id) {
+                    linter.error(QUESTLINES, format!("duplicate {}", what));
+                }
+                if quest.quest_name.is_none() {
+                    linter.warning(QUESTLINES, format!("{} has no name", what));
+                }
+                if quest.completion.is_none() {
+                    linter.error(
+                        QUESTLINES,
+                        format!("{} has no completion, so it can't be finished", what),
+                    );
+                }
+            }
+            for condition in quest
+                .conditions
+                .iter()
+                .chain(quest.choices.iter().flat_map(|c| c.conditions.iter()))
+            {
+                check_condition(linter, content, &flags, condition, &what);
+            }
+            let gotos = quest
+                .goto
+                .iter()
+                .chain(quest.choices.iter().filter_map(|c| c.goto.as_ref()));
+            for goto in gotos {
+                if !nodes.contains(goto.as_str()) {
+                    linter.error(
+                        QUESTLINES,
+                        format!("{} goes to unknown node '{}'", what, goto),
+                    );
+                }
+            }
+            if let Some(portrait) = &quest.dialog_markup.portrait {
+                let path = format!("portraits/{}", portrait);
+                if !(content.file_exists)(&path) {
+                    linter.error(
+                        QUESTLINES,
+                        format!("{} shows a missing portrait: {}", what, path),
+                    );
+                }
+            }
+            let Some(completion) = &quest.completion else {
+                continue;
+            };
+            match completion {
+                QuestCompletion::Interact { who } | QuestCompletion::TalkTo { who } => {
+                    if !has_object(who) {
+                        linter.error(
+                            QUESTLINES,
+                            format!("{} needs unknown object '{}'", what, who),
+                        );
+                    }
+                }
+                QuestCompletion::ReachLocation { loc_id, .. } => {
+                    if !is_location(maps, loc_id) {
+                        linter.error(
+                            QUESTLINES,
+                            format!("{} leads to unknown location '{}'", what, loc_id),
+                        );
+                    }
+                }
+                QuestCompletion::CollectItem { items: needed } => {
+                    for item in needed {
+                        if !items.contains(item.as_str()) {
+                            linter.error(
+                                QUESTLINES,
+                                format!("{} needs item '{}', which no object gives", what, item),
+                            );
+                        }
+                    }
+                }
+                QuestCompletion::VisitBuildings { count, loc_ids } => {
+                    let buildings = match loc_ids {
+                        Some(loc_ids) => {
+                            for loc_id in loc_ids {
+                                if maps.get_inside(loc_id).is_none() {
+                                    linter.error(
+                                        QUESTLINES,
+                                        format!("{} counts unknown building '{}'", what, loc_id),
+                                    );
+                                }
+                            }
+                            loc_ids.len()
+                        }
+                        None => maps.insides.len(),
+                    };
+                    if *count as usize > buildings {
+                        linter.error(
+                            QUESTLINES,
+                            format!(
+                                "{} needs {} buildings, but there are only {}",
+                                what, count, buildings
+                            ),
+                        );
+                    }
+                }
+                QuestCompletion::AnswerQuestion {
+                    options, answer, ..
+                } => {
+                    if *answer >= options.len() {
+                        linter.error(
+                            QUESTLINES,
+                            format!(
+                                "{} has answer {}, but only {} options",
+                                what,
+                                answer,
+                                options.len()
+                            ),
+                        );
+                    }
+                }
+                QuestCompletion::Timer { .. } => (),
+            }
+        }
+    }
+}
+
+/// Finds questlines whose prerequisites can never hold, starting from the ones a new player
+/// gets and following what completing each questline unlocks.
+fn check_reachability(linter: &mut Linter, questlines: &[Questline]) {
+    let mut reachable: BTreeSet<u16> = BTreeSet::new();
+    loop {
+        let flags = flags_set(questlines.iter().filter(|ql| reachable.contains(&ql.id)));
+        let can_hold = |condition: &Condition| match condition {
+            Condition::QuestComplete { quest } => {
+                reachable.contains(&quest.questline) && find_quest(questlines, *quest).is_some()
+            }
+            Condition::QuestlineComplete { questline } => reachable.contains(questline),
+            Condition::Flag { flag } => flags.contains(flag.as_str()),
+            Condition::NotFlag { .. } | Condition::Visited { .. } => true,
+        };
+        let newly: Vec<u16> = questlines
+            .iter()
+            .filter(|ql| !reachable.contains(&ql.id))
+            .filter(|ql| match &ql.prerequisites {
+                Some(prerequisites) => prerequisites.iter().all(can_hold),
+                None => questlines
+                    .iter()
+                    .map(|other| other.id)
+                    .filter(|id| *id < ql.id)
+                    .max()
+                    .is_none_or(|previous| reachable.contains(&previous)),
+            })
+            .map(|ql| ql.id)
+            .collect();
+        if newly.is_empty() {
+            break;
+        }
+        reachable.extend(newly);
+    }
+    for questline in questlines {
+        if !reachable.contains(&questline.id) {
+            linter.error(
+                QUESTLINES,
+                format!("questline {} can never start", questline.id),
+            );
+        }
+    }
+}
+
+fn check_translations(linter: &mut Linter, content: &LintContent) {
+    for (locale, table) in content.locales {
+        let file = format!("locales/{}.json", locale);
+        for questline in &content.game_data.questlines {
+            for (i, line) in questline.quests.iter().enumerate() {
+                if line.node.is_some() {
+                    continue;
+                }
+                let prefix = format!("line.{}.{}.", questline.id, i);
+                if !table.keys().any(|key| key.starts_with(&prefix)) {
+                    continue;
+                }
+                linter.error(
+                    &file,
+                    format!(
+                        "line {} of questline {} is translated by its position, which is \
+                         ignored since it changes when lines are reordered; give it a node",
+                        i, questline.id
+                    ),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const MAP_DATA_JSON: &str = r#"{
+        "outside": [{"loc_id": "hall", "tl_corner": [0, 0], "br_corner": [1, 1], "label": "Hall"}],
+        "insides": [{"loc_id": "hall", "tilemap_path": "hall.json",
+                     "tilemap_texture_mappings": [["tiles", "tiles.png"]],
+                     "spawn_location": [1, 1]}]
+    }"#;
+    const HALL_JSON: &str = r#"{"tilewidth": 32, "tileheight": 32, "layers": [
+        {"name": "Objects", "type": "objectgroup", "objects": [
+            {"id": 1, "name": "", "type": "exit", "x": 0, "y": 0, "width": 32, "height": 32,
+             "properties": [{"name": "target", "type": "string", "value": "outside"}]},
+            {"id": 2, "name": "bob", "type": "npc", "x": 64, "y": 64, "properties": [
+                {"name": "sprite_map", "type": "string", "value": "tiles"},
+                {"name": "tile_id", "type": "int", "value": 0}]}
+        ]}
+    ]}"#;
+    const CLEAN_QUESTLINES: &str = r#"[{"id": 0, "quests": [
+        {"speaker": "Bob", "dialog": "Hi", "quest_id": 0, "quest_name": "Meet Bob",
+         "completion": {"type": "talk_to", "who": "bob"}}
+    ]}]"#;
+
+    /// Writes a small but complete set of assets to a new directory, with `changes` written
+    /// over it. Files changed to `None` are left out.
+    fn assets(name: &str, changes: &[(&str, Option<&str>)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gv-lint-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("maps")).unwrap();
+        fs::create_dir_all(dir.join("locales")).unwrap();
+        let mut files = vec![
+            (MAP_DATA, Some(MAP_DATA_JSON)),
+            (QUESTLINES, Some(CLEAN_QUESTLINES)),
+            ("maps/hall.json", Some(HALL_JSON)),
+            ("maps/tiles.png", Some("png")),
+        ];
+        files.extend_from_slice(changes);
+        let files: std::collections::BTreeMap<_, _> = files.into_iter().collect();
+        for (file, contents) in files {
+            if let Some(contents) = contents {
+                fs::write(dir.join(file), contents).unwrap();
+            }
+        }
+        dir
+    }
+
+    fn lint_assets(name: &str, changes: &[(&str, Option<&str>)]) -> Result<Vec<Issue>, String> {
+        let dir = assets(name, changes);
+        let issues = lint_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        issues
+    }
+
+    #[test]
+    fn clean_assets_have_no_issues() {
+        let issues = lint_assets("clean", &[]).unwrap();
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn missing_tilesets_are_fatal() {
+        let issues = lint_assets("tileset", &[("maps/tiles.png", None)]).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Fatal);
+        assert_eq!(issues[0].file, MAP_DATA);
+    }
+
+    #[test]
+    fn unreadable_tiled_maps_are_fatal() {
+        let issues = lint_assets("tiled", &[("maps/hall.json", Some("{"))]).unwrap();
+        assert!(issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Fatal && issue.file == "maps/hall.json"));
+    }
+
+    #[test]
+    fn broken_references_are_errors() {
+        let questlines = r#"[{"id": 0, "quests": [
+            {"speaker": "Bob", "dialog": "Hi", "quest_id": 0, "quest_name": "Go",
+             "completion": {"type": "reach_location", "loc_id": "moon"}, "goto": "nowhere"}
+        ]}]"#;
+        let issues = lint_assets("references", &[(QUESTLINES, Some(questlines))]).unwrap();
+        assert_eq!(issues.len(), 2, "{:?}", issues);
+        assert!(issues.iter().all(|issue| issue.severity == Severity::Error));
+    }
+
+    #[test]
+    fn unreadable_locales_are_errors() {
+        let issues = lint_assets("locale", &[("locales/cy.json", Some("[]"))]).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].file, "locales/cy.json");
+    }
+
+    #[test]
+    fn missing_content_files_fail() {
+        assert!(lint_assets("content", &[(QUESTLINES, None)]).is_err());
+    }
+
+    const TRANSLATED_QUESTLINES: &str = r#"[{"id": 0, "quests": [
+        {"speaker": "Bob", "dialog": "Hi", "quest_id": 0, "completion": {"type": "timer", "seconds": 1.0}},
+        {"speaker": "Bob", "dialog": "Bye", "node": "bye"}
+    ]}]"#;
+
+    fn lint_translations(table: &[(&str, &str)]) -> Vec<Issue> {
+        let maps = Maps::from_json(r#"{"outside": [], "insides": []}"#).unwrap();
+        let game_data = GameData::from_json(TRANSLATED_QUESTLINES, "[]").unwrap();
+        let table = table
+            .iter()
+            .map(|(key, text)| (key.to_string(), text.to_string()))
+            .collect();
+        let mut linter = Linter { issues: Vec::new() };
+        check_translations(
+            &mut linter,
+            &LintContent {
+                maps: &maps,
+                game_data: &game_data,
+                tiled_maps: &[],
+                file_exists: &|_| true,
+                locales: &[(String::from("cy"), table)],
+            },
+        );
+        linter.issues
+    }
+
+    #[test]
+    fn translated_lines_need_a_node() {
+        let issues = lint_translations(&[("line.0.0.dialog", "Helo")]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].file, "locales/cy.json");
+    }
+
+    #[test]
+    fn lines_keyed_by_node_can_be_translated() {
+        let issues = lint_translations(&[("line.0.bye.dialog", "Hwyl"), ("line.0.10.dialog", "")]);
+        assert!(issues.is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+//! String tables for translating the game.
+//!
+//! Each locale has a flat `{ "key": "text" }` table in `<assets>/locales/<locale>.json`.
+//! UI strings are keyed by name (`login.title`) and their English text ships with the game.
+//! Content is keyed by where the text lives:
+//!
+//! - `questline.<id>.name`
+//! - `line.<questline>.<node>.speaker|dialog|quest_name|description|hint|question`
+//! - `line.<questline>.<node>.option.<n>` and `line.<questline>.<node>.choice.<n>`
+//! - `location.<loc_id>` for map labels
+//!
+//! Only lines with a `node` can be translated, since a line's index changes when lines are
+//! reordered. `gv-untranslated` lists the lines that need one.
+//!
+//! Content falls back to the English text in the content files when a key is missing.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tracing::warn;
+
+use crate::{
+    map_data::Maps,
+    quest_data::{Quest, QuestCompletion, Questline},
+};
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// English UI strings.
+const BUILTIN_ENGLISH: &str = include_str!("../locales/en.json");
+
+pub type StringTable = HashMap<String, String>;
+
+pub fn parse_table(json: &str) -> Result<StringTable, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// The English UI strings that ship with the game.
+pub fn builtin_english() -> StringTable {
+    parse_table(BUILTIN_ENGLISH).expect("Built in English strings are invalid")
+}
+
+/// Looks strings up in the player's locale, falling back to English.
+#[derive(Clone, Debug)]
+pub struct Localization {
+    pub locale: String,
+    strings: StringTable,
+    english: StringTable,
+}
+
+impl Localization {
+    /// `english` overrides the built in English strings.
+    pub fn new(locale: &str, strings: StringTable, english: StringTable) -> Self {
+        let mut builtin = builtin_english();
+        builtin.extend(english);
+        Localization {
+            locale: locale.to_string(),
+            strings,
+            english: builtin,
+        }
+    }
+
+    /// Built in English only.
+    pub fn english() -> Self {
+        Localization::new(DEFAULT_LOCALE, StringTable::new(), StringTable::new())
+    }
+
+    pub fn lookup(&self, key: &str) -> Option<&str> {
+        self.strings
+            .get(key)
+            .or_else(|| self.english.get(key))
+            .map(String::as_str)
+    }
+
+    /// The string for `key`, or the key itself when no table has it.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(key).unwrap_or_else(|| {
+            warn!("Missing string '{}'", key);
+            key
+        })
+    }
+
+    /// The string for `key` with `{0}`, `{1}`, ... replaced by `args`.
+    pub fn format(&self, key: &str, args: &[&str]) -> String {
+        let mut text = self.get(key).to_string();
+        for (i, arg) in args.iter().enumerate() {
+            text = text.replace(&format!("{{{}}}", i), arg);
+        }
+        text
+    }
+}
+
+/// Calls `f` with the key and text of every translatable string in `questlines`.
+pub fn for_each_text(questlines: &mut [Questline], mut f: impl FnMut(String, &mut String)) {
+    for questline in questlines.iter_mut() {
+        if let Some(name) = questline.name.as_mut() {
+            f(format!("questline.{}.name", questline.id), name);
+        }
+        for quest in questline.quests.iter_mut() {
+            let Some(prefix) = line_key(questline.id, quest) else {
+                continue;
+            };
+            f(format!("{}.speaker", prefix), &mut quest.speaker);
+            f(format!("{}.dialog", prefix), &mut quest.dialog);
+            if let Some(name) = quest.quest_name.as_mut() {
+                f(format!("{}.quest_name", prefix), name);
+            }
+            if let Some(description) = quest.description.as_mut() {
+                f(format!("{}.description", prefix), description);
+            }
+            if let Some(hint) = quest.hint.as_mut() {
+                f(format!("{}.hint", prefix), hint);
+            }
+            if let Some(QuestCompletion::AnswerQuestion {
+                question, options, ..
+            }) = quest.completion.as_mut()
+            {
+                f(format!("{}.question", prefix), question);
+                for (n, option) in options.iter_mut().enumerate() {
+                    f(format!("{}.option.{}", prefix, n), option);
+                }
+            }
+            for (n, choice) in quest.choices.iter_mut().enumerate() {
+                f(format!("{}.choice.{}", prefix, n), &mut choice.text);
+            }
+        }
+    }
+}
+
+/// The prefix of the keys of `line` in `questline`: `line.<questline>.<node>`. Lines without
+/// a `node` can't be translated.
+pub fn line_key(questline: u16, line: &Quest) -> Option<String> {
+    let node = line.node.as_ref()?;
+    Some(format!("line.{}.{}", questline, node))
+}
+
+/// Every key a locale can translate with its English text, sorted by key.
+pub fn translatable_keys(questlines: &[Questline], maps: &Maps) -> BTreeMap<String, String> {
+    let mut keys: BTreeMap<String, String> = builtin_english().into_iter().collect();
+    for_each_text(&mut questlines.to_vec(), |key, text| {
+        keys.insert(key, text.clone());
+    });
+    for location in &maps.outside {
+        keys.insert(
+            format!("location.{}", location.loc_id),
+            location.label.clone(),
+        );
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUESTLINES: &str = r#"[{"id": 3, "name": "Intro", "quests": [
+        {"speaker": "Bob", "dialog": "Hi", "quest_id": 0, "quest_name": "Meet Bob",
+         "node": "meet", "completion": {"type": "answer_question", "question": "Sky?",
+                                        "options": ["Blue", "Grey"], "answer": 0}},
+        {"speaker": "Bob", "dialog": "Pick", "node": "pick",
+         "choices": [{"text": "Yes"}, {"text": "No"}]},
+        {"speaker": "Bob", "dialog": "Bye"}
+    ]}]"#;
+    const MAP_DATA: &str = r#"{"insides": [], "outside": [
+        {"loc_id": "lib", "tl_corner": [0, 0], "br_corner": [1, 1], "label": "Library"}
+    ]}"#;
+
+    #[test]
+    fn keys_content_by_node() {
+        let questlines: Vec<Questline> = serde_json::from_str(QUESTLINES).unwrap();
+        let maps = Maps::from_json(MAP_DATA).unwrap();
+        let keys = translatable_keys(&questlines, &maps);
+        let expected = [
+            ("questline.3.name", "Intro"),
+            ("line.3.meet.speaker", "Bob"),
+            ("line.3.meet.quest_name", "Meet Bob"),
+            ("line.3.meet.question", "Sky?"),
+            ("line.3.meet.option.1", "Grey"),
+            ("line.3.pick.dialog", "Pick"),
+            ("line.3.pick.choice.0", "Yes"),
+            ("line.3.pick.choice.1", "No"),
+            ("location.lib", "Library"),
+        ];
+        for (key, text) in expected {
+            assert_eq!(keys.get(key).map(String::as_str), Some(text), "{}", key);
+        }
+        // The last line has no node, so it isn't offered for translation
+        assert!(!keys.keys().any(|key| key.starts_with("line.3.2.")));
+        assert!(!keys.values().any(|text| text == "Bye"));
+        assert!(keys.contains_key("login.title"));
+    }
+
+    #[test]
+    fn falls_back_to_english() {
+        let strings = parse_table(r#"{"login.title": "Mewngofnodi"}"#).unwrap();
+        let english = parse_table(r#"{"custom": "Hello {0}, {1}"}"#).unwrap();
+        let localization = Localization::new("cy", strings, english);
+        assert_eq!(localization.get("login.title"), "Mewngofnodi");
+        assert_eq!(
+            localization.format("custom", &["Bob", "Amy"]),
+            "Hello Bob, Amy"
+        );
+        assert_eq!(localization.get("missing.key"), "missing.key");
+    }
+}
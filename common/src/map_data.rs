@@ -0,0 +1,65 @@
+use glam::f32::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::Localization;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Maps {
+    /// Buildings on the fast travel map.
+    pub outside: Vec<MapLocation>,
+    pub insides: Vec<MapMeta>,
+    /// Walkable campus shown for the `outside` location. The fast travel map is used as
+    /// the outside instead when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campus: Option<MapMeta>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapLocation {
+    pub loc_id: String,
+    pub tl_corner: Vec2,
+    pub br_corner: Vec2,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapMeta {
+    pub loc_id: String,
+    pub tilemap_path: String,
+    pub tilemap_texture_mappings: Vec<(String, String)>,
+    pub spawn_location: Vec2,
+}
+
+impl Maps {
+    /// Parses the contents of `map_data.json`.
+    pub fn from_json(map_data: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str::<Maps>(map_data)
+    }
+
+    /// Replaces location labels with their `location.<loc_id>` translations.
+    pub fn localize(&mut self, strings: &Localization) {
+        for location in self.outside.iter_mut() {
+            if let Some(label) = strings.lookup(&format!("location.{}", location.loc_id)) {
+                location.label = label.to_string();
+            }
+        }
+    }
+
+    pub fn get_inside(&self, loc_id: &str) -> Option<&MapMeta> {
+        self.insides.iter().find(|m| m.loc_id == loc_id)
+    }
+
+    /// The walkable map of `loc_id`, including the campus for `outside`.
+    pub fn get_map(&self, loc_id: &str) -> Option<&MapMeta> {
+        if loc_id.eq_ignore_ascii_case("outside") {
+            self.campus.as_ref()
+        } else {
+            self.get_inside(loc_id)
+        }
+    }
+
+    /// Every walkable map: the interiors and the campus.
+    pub fn walkable_maps(&self) -> impl Iterator<Item = &MapMeta> {
+        self.insides.iter().chain(self.campus.iter())
+    }
+}
@@ -0,0 +1,531 @@
+//! Tile layers and objects of maps made in Tiled.
+//!
+//! Objects are recognised by their type (called class in some Tiled versions):
+//!
+//! - `spawn`: a point the player can be placed at, found by its name. The one named
+//!   `default` (or else the first one) is used when no spawn point is asked for.
+//! - `exit`: an area that moves the player to the location in its `target` property as
+//!   soon as they walk onto it.
+//! - `door`: like `exit`, but the player has to press Enter while standing on it.
+//! - `collider`: a rectangle or polygon the player can't walk through.
+//! - `npc` and `object`: something the player can interact with. Its id is the `object_id`
+//!   property or else its name. The sprite is the object's tile, or the `sprite_map` and
+//!   `tile_id` properties. `quests` lists the quests it's relevant to (`"0:1, 0:2"`) and
+//!   `item` names an item given to the player on interaction.
+//!
+//! Exits and doors take an optional `spawn` property naming the spawn point to use in the
+//! target location. A target of `outside` leads back to the campus map.
+//!
+//! Tile layers are drawn in the order Tiled shows them. Tiles on layers with the boolean
+//! property `collides` are solid.
+
+use glam::f32::{vec2, Vec2};
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::quest_data::{ObjectLocation, ObjectQuestRef, QuestRef, TileId};
+
+/// Size of the squares polygon colliders are filled with, in map pixels.
+const POLYGON_RESOLUTION: f32 = 8.;
+
+#[derive(Debug, Error)]
+pub enum MapError {
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("object {id} ('{name}'): {message}")]
+    Object {
+        id: u32,
+        name: String,
+        message: String,
+    },
+}
+
+/// An axis aligned area in map pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Area {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl Area {
+    pub fn new(pos: Vec2, size: Vec2) -> Self {
+        Area { pos, size }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.pos.x
+            && point.y >= self.pos.y
+            && point.x < self.pos.x + self.size.x
+            && point.y < self.pos.y + self.size.y
+    }
+
+    pub fn overlaps(&self, other: &Area) -> bool {
+        self.pos.x < other.pos.x + other.size.x
+            && other.pos.x < self.pos.x + self.size.x
+            && self.pos.y < other.pos.y + other.size.y
+            && other.pos.y < self.pos.y + self.size.y
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpawnPoint {
+    pub name: String,
+    /// Map pixels.
+    pub position: Vec2,
+}
+
+#[derive(Clone, Debug)]
+pub struct Exit {
+    pub area: Area,
+    /// `loc_id` of the location the exit leads to.
+    pub target: String,
+    /// Spawn point in the target location.
+    pub spawn: Option<String>,
+    /// Doors need the player to press Enter; other exits trigger on contact.
+    pub door: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Collider {
+    Rect(Area),
+    /// Corners in map pixels.
+    Polygon(Vec<Vec2>),
+}
+
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    pub collides: bool,
+}
+
+/// The layers and objects of a Tiled map.
+#[derive(Clone, Debug, Default)]
+pub struct MapObjects {
+    /// Tile layers from bottom to top.
+    pub layers: Vec<TileLayer>,
+    pub spawns: Vec<SpawnPoint>,
+    pub exits: Vec<Exit>,
+    pub colliders: Vec<Collider>,
+    /// NPCs and interactable objects, positioned in tiles.
+    pub objects: Vec<ObjectLocation>,
+}
+
+#[derive(Deserialize)]
+struct TiledMap {
+    layers: Vec<TiledLayer>,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+    firstgid: u32,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct TiledLayer {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+    #[serde(default)]
+    objects: Vec<TiledObject>,
+    /// Layers inside a group layer.
+    #[serde(default)]
+    layers: Vec<TiledLayer>,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    gid: Option<u32>,
+    #[serde(default)]
+    polygon: Option<Vec<TiledPoint>>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct TiledProperty {
+    name: String,
+    value: Value,
+}
+
+fn find_property(properties: &[TiledProperty], name: &str) -> Option<String> {
+    let property = properties.iter().find(|p| p.name == name)?;
+    match &property.value {
+        Value::String(value) if value.is_empty() => None,
+        Value::String(value) => Some(value.clone()),
+        value => Some(value.to_string()),
+    }
+}
+
+impl TiledObject {
+    fn area(&self) -> Area {
+        // Tile objects are anchored at their bottom left corner
+        let y = match self.gid {
+            Some(_) => self.y - self.height,
+            None => self.y,
+        };
+        Area::new(vec2(self.x, y), vec2(self.width, self.height))
+    }
+
+    fn property(&self, name: &str) -> Option<String> {
+        find_property(&self.properties, name)
+    }
+
+    fn error(&self, message: &str) -> MapError {
+        MapError::Object {
+            id: self.id,
+            name: self.name.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl MapObjects {
+    /// Reads the layers of the map of `loc_id` saved in Tiled's JSON format.
+    pub fn from_tiled_json(loc_id: &str, json: &str) -> Result<Self, MapError> {
+        let map = serde_json::from_str::<TiledMap>(json)?;
+        let mut objects = MapObjects::default();
+        objects.add_layers(&map, &map.layers, loc_id)?;
+        Ok(objects)
+    }
+
+    fn add_layers(
+        &mut self,
+        map: &TiledMap,
+        layers: &[TiledLayer],
+        loc_id: &str,
+    ) -> Result<(), MapError> {
+        for layer in layers {
+            match layer.kind.as_str() {
+                "tilelayer" => {
+                    let collides = match find_property(&layer.properties, "collides") {
+                        Some(collides) => collides == "true",
+                        None if layer.name.to_ascii_lowercase().contains("collide") => {
+                            warn!(
+                                "Layer '{}' collides because of its name; give it a 'collides' property instead",
+                                layer.name
+                            );
+                            true
+                        }
+                        None => false,
+                    };
+                    self.layers.push(TileLayer {
+                        name: layer.name.clone(),
+                        collides,
+                    });
+                }
+                "group" => self.add_layers(map, &layer.layers, loc_id)?,
+                _ => (),
+            }
+            for object in &layer.objects {
+                self.add(map, object, loc_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, map: &TiledMap, object: &TiledObject, loc_id: &str) -> Result<(), MapError> {
+        match object.class.to_ascii_lowercase().as_str() {
+            "spawn" => self.spawns.push(SpawnPoint {
+                name: object.name.clone(),
+                position: vec2(object.x, object.y),
+            }),
+            class @ ("exit" | "door") => self.exits.push(Exit {
+                area: object.area(),
+                target: object
+                    .property("target")
+                    .ok_or_else(|| object.error("exit has no 'target' property"))?,
+                spawn: object.property("spawn"),
+                door: class == "door",
+            }),
+            "collider" => self.colliders.push(match &object.polygon {
+                Some(points) => Collider::Polygon(
+                    points
+                        .iter()
+                        .map(|p| vec2(object.x + p.x, object.y + p.y))
+                        .collect(),
+                ),
+                None => Collider::Rect(object.area()),
+            }),
+            "npc" | "object" => {
+                let area = object.area();
+                self.objects.push(ObjectLocation {
+                    object_id: object
+                        .property("object_id")
+                        .or_else(|| Some(object.name.clone()).filter(|n| !n.is_empty()))
+                        .ok_or_else(|| object.error("object has no name or 'object_id'"))?,
+                    loc_id: loc_id.to_string(),
+                    sprite: object_sprite(map, object)?,
+                    position: vec2(area.pos.x / map.tilewidth, area.pos.y / map.tileheight),
+                    relevant_quest_ids: object
+                        .property("quests")
+                        .map(|quests| parse_quests(&quests))
+                        .transpose()
+                        .map_err(|e| object.error(&e))?,
+                    item: object.property("item"),
+                });
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Every area the player can't walk through. Polygons are filled with small squares.
+    pub fn solid_areas(&self) -> Vec<Area> {
+        let mut areas = Vec::new();
+        for collider in &self.colliders {
+            match collider {
+                Collider::Rect(area) => areas.push(*area),
+                Collider::Polygon(points) => areas.extend(fill_polygon(points)),
+            }
+        }
+        areas
+    }
+
+    /// The spawn point called `name`, falling back to the default spawn point.
+    pub fn spawn_point(&self, name: Option<&str>) -> Option<Vec2> {
+        name.and_then(|name| self.spawns.iter().find(|s| s.name == name))
+            .or_else(|| self.spawns.iter().find(|s| s.name == "default"))
+            .or_else(|| self.spawns.first())
+            .map(|s| s.position)
+    }
+}
+
+fn object_sprite(map: &TiledMap, object: &TiledObject) -> Result<TileId, MapError> {
+    if let Some(gid) = object.gid {
+        // The top bits of a gid hold flip flags
+        let gid = gid & 0x0fff_ffff;
+        let tileset = map
+            .tilesets
+            .iter()
+            .filter(|t| t.firstgid <= gid)
+            .max_by_key(|t| t.firstgid)
+            .ok_or_else(|| object.error("tile isn't in any tileset"))?;
+        return Ok(TileId {
+            sprite_map: tileset.name.clone(),
+            tile_id: gid - tileset.firstgid,
+        });
+    }
+    match (object.property("sprite_map"), object.property("tile_id")) {
+        (Some(sprite_map), Some(tile_id)) => Ok(TileId {
+            sprite_map,
+            tile_id: tile_id
+                .parse()
+                .map_err(|_| object.error("'tile_id' isn't a number"))?,
+        }),
+        _ => {
+            Err(object
+                .error("object needs to be a tile or have 'sprite_map' and 'tile_id' properties"))
+        }
+    }
+}
+
+/// Parses a comma separated list of `questline:quest` pairs.
+fn parse_quests(quests: &str) -> Result<Vec<ObjectQuestRef>, String> {
+    quests
+        .split(',')
+        .map(str::trim)
+        .filter(|quest| !quest.is_empty())
+        .map(|quest| {
+            let (questline, id) = quest
+                .split_once(':')
+                .ok_or(format!("quest '{}' isn't 'questline:quest'", quest))?;
+            match (questline.trim().parse(), id.trim().parse()) {
+                (Ok(questline), Ok(id)) => Ok(ObjectQuestRef::Quest(QuestRef::new(questline, id))),
+                _ => Err(format!("quest '{}' isn't 'questline:quest'", quest)),
+            }
+        })
+        .collect()
+}
+
+/// Covers the inside of a polygon with `POLYGON_RESOLUTION` high strips.
+fn fill_polygon(points: &[Vec2]) -> Vec<Area> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    let min = points.iter().fold(Vec2::MAX, |min, p| min.min(*p));
+    let max = points.iter().fold(Vec2::MIN, |max, p| max.max(*p));
+    let mut areas = Vec::new();
+    let mut y = min.y;
+    while y < max.y {
+        let mut x = min.x;
+        let mut run_start = None;
+        while x < max.x {
+            let center = vec2(x, y) + Vec2::splat(POLYGON_RESOLUTION / 2.);
+            match (contains_point(points, center), run_start) {
+                (true, None) => run_start = Some(x),
+                (false, Some(start)) => {
+                    areas.push(strip(start, x, y));
+                    run_start = None;
+                }
+                _ => (),
+            }
+            x += POLYGON_RESOLUTION;
+        }
+        if let Some(start) = run_start {
+            areas.push(strip(start, x, y));
+        }
+        y += POLYGON_RESOLUTION;
+    }
+    areas
+}
+
+fn strip(start: f32, end: f32, y: f32) -> Area {
+    Area::new(vec2(start, y), vec2(end - start, POLYGON_RESOLUTION))
+}
+
+/// Even-odd test of whether `point` is inside the polygon.
+fn contains_point(points: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    let mut previous = points[points.len() - 1];
+    for current in points {
+        if (current.y > point.y) != (previous.y > point.y)
+            && point.x
+                < (previous.x - current.x) * (point.y - current.y) / (previous.y - current.y)
+                    + current.x
+        {
+            inside = !inside;
+        }
+        previous = *current;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area_sum(areas: &[Area]) -> f32 {
+        areas.iter().map(|a| a.size.x * a.size.y).sum()
+    }
+
+    #[test]
+    fn fills_a_square_with_full_strips() {
+        let square = [vec2(0., 0.), vec2(32., 0.), vec2(32., 32.), vec2(0., 32.)];
+        let areas = fill_polygon(&square);
+        assert_eq!(areas.len(), 4);
+        assert!(areas.iter().all(|a| a.size == vec2(32., 8.)));
+        assert_eq!(area_sum(&areas), 32. * 32.);
+    }
+
+    #[test]
+    fn splits_strips_around_a_concave_gap() {
+        // A U shape: two 16 wide legs with a 16 wide gap between them in the top half
+        let u = [
+            vec2(0., 0.),
+            vec2(16., 0.),
+            vec2(16., 16.),
+            vec2(32., 16.),
+            vec2(32., 0.),
+            vec2(48., 0.),
+            vec2(48., 32.),
+            vec2(0., 32.),
+        ];
+        let areas = fill_polygon(&u);
+        let top: Vec<&Area> = areas.iter().filter(|a| a.pos.y < 16.).collect();
+        assert_eq!(top.len(), 4);
+        assert!(!areas.iter().any(|a| a.contains(vec2(24., 8.))));
+        assert!(areas.iter().any(|a| a.contains(vec2(24., 24.))));
+        assert_eq!(area_sum(&areas), 48. * 32. - 16. * 16.);
+    }
+
+    #[test]
+    fn approximates_a_triangle() {
+        let triangle = [vec2(0., 0.), vec2(64., 64.), vec2(0., 64.)];
+        let filled = area_sum(&fill_polygon(&triangle));
+        let exact = 64. * 64. / 2.;
+        assert!(
+            (filled - exact).abs() <= 64. * POLYGON_RESOLUTION,
+            "{}",
+            filled
+        );
+    }
+
+    #[test]
+    fn ignores_degenerate_polygons() {
+        assert!(fill_polygon(&[vec2(0., 0.), vec2(8., 8.)]).is_empty());
+    }
+
+    #[test]
+    fn reads_tiled_objects() {
+        let json = r#"{
+            "tilewidth": 32, "tileheight": 32,
+            "tilesets": [{"firstgid": 1, "name": "objects"}],
+            "layers": [
+                {"name": "Walls", "type": "tilelayer",
+                 "properties": [{"name": "collides", "type": "bool", "value": true}]},
+                {"name": "Objects", "type": "objectgroup", "objects": [
+                    {"id": 1, "name": "default", "type": "spawn", "x": 40, "y": 50},
+                    {"id": 2, "name": "", "type": "door", "x": 0, "y": 0,
+                     "width": 32, "height": 32, "properties": [
+                        {"name": "target", "type": "string", "value": "outside"},
+                        {"name": "spawn", "type": "string", "value": "library"}]},
+                    {"id": 3, "name": "", "class": "collider", "x": 10, "y": 10,
+                     "polygon": [{"x": 0, "y": 0}, {"x": 8, "y": 0}, {"x": 0, "y": 8}]},
+                    {"id": 4, "name": "bob", "type": "npc", "gid": 3, "x": 64, "y": 96,
+                     "width": 32, "height": 32, "properties": [
+                        {"name": "quests", "type": "string", "value": "0:1, 2:0"}]}
+                ]}
+            ]
+        }"#;
+        let objects = MapObjects::from_tiled_json("lib", json).unwrap();
+        assert_eq!(objects.layers.len(), 1);
+        assert!(objects.layers[0].collides);
+        assert_eq!(objects.spawn_point(Some("missing")), Some(vec2(40., 50.)));
+        assert!(objects.exits[0].door);
+        assert_eq!(objects.exits[0].target, "outside");
+        assert_eq!(objects.exits[0].spawn.as_deref(), Some("library"));
+        assert!(
+            matches!(&objects.colliders[0], Collider::Polygon(points) if points[1] == vec2(18., 10.))
+        );
+        let bob = &objects.objects[0];
+        assert_eq!(bob.object_id, "bob");
+        assert_eq!(bob.sprite.sprite_map, "objects");
+        assert_eq!(bob.sprite.tile_id, 2);
+        assert_eq!(bob.position, vec2(2., 2.));
+        assert_eq!(
+            bob.relevant_quest_ids,
+            Some(vec![
+                ObjectQuestRef::Quest(QuestRef::new(0, 1)),
+                ObjectQuestRef::Quest(QuestRef::new(2, 0))
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_exits_without_a_target() {
+        let json = r#"{"tilewidth": 32, "tileheight": 32, "layers": [
+            {"name": "Objects", "type": "objectgroup", "objects": [
+                {"id": 7, "name": "", "type": "exit", "x": 0, "y": 0, "width": 8, "height": 8}
+            ]}
+        ]}"#;
+        assert!(MapObjects::from_tiled_json("lib", json).is_err());
+    }
+}
@@ -0,0 +1,440 @@
+//! Dialogue text markup.
+//!
+//! - `{name}`, `{location}`, `{completed_quests}`, `{completed_questlines}`, `{items}` and
+//!   `{flag:NAME}` are replaced with values from the player's state. `<name>` is kept as an
+//!   alias for `{name}`.
+//! - `[color=red]..[/color]` (named colors or `#rrggbb`) and `[em]..[/em]` style text.
+//! - `[pause=0.5]` holds back the rest of the line for half a second.
+//! - `[portrait=professor.png]` shows a portrait next to the line.
+//! - `[[` and `{{` produce a literal `[` and `{`.
+
+use std::{fmt, iter::Peekable, str::Chars};
+
+use crate::{
+    quest_data::{Quest, Questline},
+    ClientState,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TextStyle {
+    /// RGBA color; the window's label color when missing.
+    pub color: Option<[u8; 4]>,
+    pub emphasis: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Variable {
+    Name,
+    Location,
+    CompletedQuests,
+    CompletedQuestlines,
+    Items,
+    Flag(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment {
+    Text(String, TextStyle),
+    Var(Variable, TextStyle),
+    Pause(f32),
+}
+
+/// A line of dialogue parsed from markup.
+#[derive(Clone, Debug, Default)]
+pub struct Markup {
+    pub segments: Vec<Segment>,
+    pub portrait: Option<String>,
+}
+
+/// A markup problem in a content file. `line` is 0 when the text couldn't be located.
+#[derive(Clone, Debug)]
+pub struct MarkupError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl Variable {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim() {
+            "name" => Ok(Variable::Name),
+            "location" => Ok(Variable::Location),
+            "completed_quests" => Ok(Variable::CompletedQuests),
+            "completed_questlines" => Ok(Variable::CompletedQuestlines),
+            "items" => Ok(Variable::Items),
+            other => match other.strip_prefix("flag:") {
+                Some(flag) if !flag.is_empty() => Ok(Variable::Flag(flag.to_string())),
+                _ => Err(format!("unknown variable '{{{}}}'", other)),
+            },
+        }
+    }
+
+    pub fn value(&self, state: &ClientState) -> String {
+        match self {
+            Variable::Name => state.username.clone(),
+            Variable::Location => state.location.clone(),
+            Variable::CompletedQuests => state.complete_quest_ids.len().to_string(),
+            Variable::CompletedQuestlines => state.complete_questlines.len().to_string(),
+            Variable::Items => state.inventory.len().to_string(),
+            Variable::Flag(flag) => {
+                if state.flags.contains(flag) {
+                    "yes".to_string()
+                } else {
+                    "no".to_string()
+                }
+            }
+        }
+    }
+}
+
+enum Tag {
+    Color,
+    Emphasis,
+}
+
+impl Markup {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut markup = Markup::default();
+        let mut style = TextStyle::default();
+        let mut open_tags: Vec<(Tag, TextStyle)> = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '[' | '{' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    text.push(c);
+                }
+                '<' if source_follows(&chars, "name>") => {
+                    for _ in 0.."name>".len() {
+                        chars.next();
+                    }
+                    markup.flush(&mut text, style);
+                    markup.segments.push(Segment::Var(Variable::Name, style));
+                }
+                '{' => {
+                    let name = read_until(&mut chars, '}')
+                        .ok_or(format!("unclosed '{{' in \"{}\"", source))?;
+                    markup.flush(&mut text, style);
+                    markup
+                        .segments
+                        .push(Segment::Var(Variable::parse(&name)?, style));
+                }
+                '[' => {
+                    let tag = read_until(&mut chars, ']')
+                        .ok_or(format!("unclosed '[' in \"{}\"", source))?;
+                    markup.flush(&mut text, style);
+                    let (name, value) = match tag.split_once('=') {
+                        Some((name, value)) => (name.trim(), Some(value.trim())),
+                        None => (tag.trim(), None),
+                    };
+                    match (name, value) {
+                        ("color", Some(value)) => {
+                            open_tags.push((Tag::Color, style));
+                            style.color = Some(parse_color(value)?);
+                        }
+                        ("em", None) => {
+                            open_tags.push((Tag::Emphasis, style));
+                            style.emphasis = true;
+                        }
+                        ("/color", None) => match open_tags.pop() {
+                            Some((Tag::Color, previous)) => style = previous,
+                            _ => return Err("[/color] without matching [color]".to_string()),
+                        },
+                        ("/em", None) => match open_tags.pop() {
+                            Some((Tag::Emphasis, previous)) => style = previous,
+                            _ => return Err("[/em] without matching [em]".to_string()),
+                        },
+                        ("pause", Some(value)) => {
+                            let seconds = value
+                                .parse::<f32>()
+                                .ok()
+                                .filter(|s| *s >= 0.)
+                                .ok_or(format!("invalid pause '{}'", value))?;
+                            markup.segments.push(Segment::Pause(seconds));
+                        }
+                        ("portrait", Some(value)) => {
+                            if markup.portrait.is_some() {
+                                return Err("more than one portrait on a line".to_string());
+                            }
+                            markup.portrait = Some(value.to_string());
+                        }
+                        _ => return Err(format!("unknown tag '[{}]'", tag)),
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+        markup.flush(&mut text, style);
+        match open_tags.last() {
+            Some((Tag::Color, _)) => Err("[color] is never closed".to_string()),
+            Some((Tag::Emphasis, _)) => Err("[em] is never closed".to_string()),
+            None => Ok(markup),
+        }
+    }
+
+    fn flush(&mut self, text: &mut String, style: TextStyle) {
+        if !text.is_empty() {
+            self.segments
+                .push(Segment::Text(std::mem::take(text), style));
+        }
+    }
+
+    /// Seconds it takes to reveal the whole line at `chars_per_second`, including pauses.
+    /// A speed of 0 or less shows text instantly.
+    pub fn reveal_time(&self, state: &ClientState, chars_per_second: f32) -> f64 {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text, _) => typing_time(text, chars_per_second),
+                Segment::Var(var, _) => typing_time(&var.value(state), chars_per_second),
+                Segment::Pause(seconds) => *seconds as f64,
+            })
+            .sum()
+    }
+
+    /// The styled text visible `elapsed` seconds after the line was opened, typed out at
+    /// `chars_per_second`.
+    pub fn reveal(
+        &self,
+        state: &ClientState,
+        elapsed: f64,
+        chars_per_second: f32,
+    ) -> Vec<(String, TextStyle)> {
+        let mut runs = Vec::new();
+        let mut remaining = elapsed;
+        for segment in &self.segments {
+            let (text, style) = match segment {
+                Segment::Text(text, style) => (text.clone(), *style),
+                Segment::Var(var, style) => (var.value(state), *style),
+                Segment::Pause(seconds) => {
+                    remaining -= *seconds as f64;
+                    if remaining < 0. {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            let time = typing_time(&text, chars_per_second);
+            if remaining >= time {
+                remaining -= time;
+                runs.push((text, style));
+                continue;
+            }
+            let shown = (remaining * chars_per_second as f64) as usize;
+            if shown > 0 {
+                runs.push((text.chars().take(shown).collect(), style));
+            }
+            break;
+        }
+        runs
+    }
+
+    /// The whole line without styling.
+    pub fn plain_text(&self, state: &ClientState) -> String {
+        self.reveal(state, f64::MAX, 0.)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+}
+
+fn typing_time(text: &str, chars_per_second: f32) -> f64 {
+    if chars_per_second <= 0. {
+        0.
+    } else {
+        text.chars().count() as f64 / chars_per_second as f64
+    }
+}
+
+fn source_follows(chars: &Peekable<Chars>, expected: &str) -> bool {
+    chars.clone().take(expected.len()).eq(expected.chars())
+}
+
+fn read_until(chars: &mut Peekable<Chars>, end: char) -> Option<String> {
+    let mut contents = String::new();
+    for c in chars.by_ref() {
+        if c == end {
+            return Some(contents);
+        }
+        contents.push(c);
+    }
+    None
+}
+
+fn parse_color(value: &str) -> Result<[u8; 4], String> {
+    let color = match value.to_ascii_lowercase().as_str() {
+        "white" => [255, 255, 255, 255],
+        "black" => [0, 0, 0, 255],
+        "gray" | "grey" => [120, 120, 120, 255],
+        "red" => [200, 40, 40, 255],
+        "green" => [40, 160, 40, 255],
+        "blue" => [40, 80, 200, 255],
+        "yellow" => [220, 190, 30, 255],
+        "orange" => [230, 130, 20, 255],
+        "purple" => [140, 60, 180, 255],
+        hex => {
+            let hex = hex
+                .strip_prefix('#')
+                .filter(|h| h.len() == 6)
+                .ok_or(format!("unknown color '{}'", value))?;
+            let channel = |i: usize| {
+                u8::from_str_radix(&hex[i..i + 2], 16)
+                    .map_err(|_| format!("unknown color '{}'", value))
+            };
+            [channel(0)?, channel(2)?, channel(4)?, 255]
+        }
+    };
+    Ok(color)
+}
+
+/// Parses the markup of every speaker, dialog line, description and choice in `questlines`,
+/// which were deserialized from `source`. Errors point at the line of `source` holding the
+/// text.
+pub fn parse_questlines(questlines: &mut [Questline], source: &str) -> Vec<MarkupError> {
+    let mut errors = Vec::new();
+    let mut cursor = 0;
+    for questline in questlines.iter_mut() {
+        for quest in questline.quests.iter_mut() {
+            let mut entry_end = cursor;
+            let mut parse = |text: &str, errors: &mut Vec<MarkupError>| -> Markup {
+                let (line, position) = locate(source, text, cursor);
+                entry_end = entry_end.max(position);
+                Markup::parse(text).unwrap_or_else(|message| {
+                    errors.push(MarkupError {
+                        file: "questlines.json".to_string(),
+                        line,
+                        message,
+                    });
+                    Markup::default()
+                })
+            };
+            parse_quest(quest, &mut parse, &mut errors);
+            cursor = entry_end;
+        }
+    }
+    errors
+}
+
+pub(crate) fn parse_quest(
+    quest: &mut Quest,
+    parse: &mut impl FnMut(&str, &mut Vec<MarkupError>) -> Markup,
+    errors: &mut Vec<MarkupError>,
+) {
+    quest.speaker_markup = parse(&quest.speaker, errors);
+    quest.dialog_markup = parse(&quest.dialog, errors);
+    if let Some(description) = &quest.description {
+        quest.description_markup = parse(description, errors);
+    }
+    for choice in quest.choices.iter_mut() {
+        choice.text_markup = parse(&choice.text, errors);
+    }
+}
+
+/// Finds the line of `source` where the JSON string `text` appears, searching from `from`.
+fn locate(source: &str, text: &str, from: usize) -> (usize, usize) {
+    let needle = serde_json::to_string(text).unwrap_or_default();
+    let position = source[from..]
+        .find(&needle)
+        .map(|p| p + from)
+        .or_else(|| source.find(&needle));
+    match position {
+        Some(position) => (source[..position].matches('\n').count() + 1, position),
+        None => (0, from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(color: Option<[u8; 4]>, emphasis: bool) -> TextStyle {
+        TextStyle { color, emphasis }
+    }
+
+    #[test]
+    fn parses_variables_and_the_name_alias() {
+        let markup = Markup::parse("Hi <name>, you have {items} items. {flag:met_bob}").unwrap();
+        let plain = TextStyle::default();
+        assert_eq!(
+            markup.segments,
+            vec![
+                Segment::Text("Hi ".to_string(), plain),
+                Segment::Var(Variable::Name, plain),
+                Segment::Text(", you have ".to_string(), plain),
+                Segment::Var(Variable::Items, plain),
+                Segment::Text(" items. ".to_string(), plain),
+                Segment::Var(Variable::Flag("met_bob".to_string()), plain),
+            ]
+        );
+    }
+
+    #[test]
+    fn styles_nest_and_restore() {
+        let markup = Markup::parse("[color=#ff0000]red [em]both[/em][/color] plain").unwrap();
+        let red = Some([255, 0, 0, 255]);
+        assert_eq!(
+            markup.segments,
+            vec![
+                Segment::Text("red ".to_string(), style(red, false)),
+                Segment::Text("both".to_string(), style(red, true)),
+                Segment::Text(" plain".to_string(), TextStyle::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_pauses_portraits_and_escapes() {
+        let markup = Markup::parse("[portrait=bob.png]Well[pause=0.5] [[sic] {{x}").unwrap();
+        assert_eq!(markup.portrait.as_deref(), Some("bob.png"));
+        assert_eq!(markup.segments[1], Segment::Pause(0.5));
+        let state = ClientState::new("tester");
+        assert_eq!(markup.plain_text(&state), "Well [sic] {x}");
+    }
+
+    #[test]
+    fn rejects_broken_markup() {
+        for source in [
+            "{nope}",
+            "{name",
+            "[em]open",
+            "[/color]",
+            "[color=red]x[/em]",
+            "[color=#12345]x[/color]",
+            "[pause=-1]",
+            "[portrait=a.png][portrait=b.png]",
+            "[wave]",
+        ] {
+            assert!(Markup::parse(source).is_err(), "{} parsed", source);
+        }
+    }
+
+    #[test]
+    fn reveals_text_over_time() {
+        let markup = Markup::parse("ab[pause=1]cd").unwrap();
+        let state = ClientState::new("tester");
+        assert_eq!(markup.reveal_time(&state, 2.), 3.);
+        let text = |elapsed| -> String {
+            markup
+                .reveal(&state, elapsed, 2.)
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect()
+        };
+        assert_eq!(text(0.5), "a");
+        assert_eq!(text(1.5), "ab");
+        assert_eq!(text(2.5), "abc");
+        assert_eq!(text(3.), "abcd");
+    }
+}
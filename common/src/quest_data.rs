@@ -0,0 +1,654 @@
+use glam::f32::Vec2;
+use serde::{Deserialize, Serialize};
+
+use thiserror::Error;
+
+use crate::{
+    locale::{for_each_text, Localization},
+    map_objects::MapObjects,
+    markup::{parse_quest, parse_questlines, Markup, MarkupError},
+    ClientState, SAVE_VERSION,
+};
+
+#[derive(Debug, Error)]
+pub enum ContentError {
+    #[error("{file}: {source}")]
+    Json {
+        file: &'static str,
+        source: serde_json::Error,
+    },
+    #[error("{}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))]
+    Markup(Vec<MarkupError>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GameData {
+    pub questlines: Vec<Questline>,
+    pub object_locations: Vec<ObjectLocation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectLocation {
+    pub object_id: String,
+    pub loc_id: String,
+    pub sprite: TileId,
+    pub position: Vec2,
+    pub relevant_quest_ids: Option<Vec<ObjectQuestRef>>,
+    /// Item added to the player's inventory when they interact with this object.
+    #[serde(default)]
+    pub item: Option<String>,
+}
+
+/// Identifies a quest by the questline it belongs to and its id within that questline.
+///
+/// Serialized as a `[questline, quest]` pair to keep saves small; content files may also
+/// use the `{ "questline": .., "quest": .. }` form.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(from = "QuestRefRepr", into = "(u16, u16)")]
+pub struct QuestRef {
+    pub questline: u16,
+    pub quest: u16,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuestRefRepr {
+    Pair(u16, u16),
+    Named { questline: u16, quest: u16 },
+}
+
+/// A quest listed in an object's `relevant_quest_ids`. Content written before quests were
+/// identified by `QuestRef` uses bare `questline + quest` sums, which `GameData::from_json`
+/// resolves against the questlines. Sums that match no quest are kept as they are.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ObjectQuestRef {
+    Quest(QuestRef),
+    LegacySum(u16),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TileId {
+    pub sprite_map: String,
+    pub tile_id: u32,
+}
+
+/// Writes questlines as `questlines.json`: pretty printed, fields in declaration order and
+/// unset optional fields left out, so saving the same content always gives the same file.
+pub fn questlines_to_json(questlines: &[Questline]) -> String {
+    let mut json = serde_json::to_string_pretty(questlines).unwrap();
+    json.push('\n');
+    json
+}
+
+/// `current_questline_id` of a player who has no questline in focus.
+pub const NO_QUESTLINE: u16 = u16::MAX;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Questline {
+    pub id: u16,
+    /// Shown when the player picks between branches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Conditions that must all hold before the questline starts. Questlines without
+    /// prerequisites follow the questline with the next lower id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prerequisites: Option<Vec<Condition>>,
+    /// Questlines sharing a branch are alternative paths: the player picks one and the
+    /// others are never started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    pub quests: Vec<Quest>,
+}
+
+/// A check against the player's progress, used by questline prerequisites and to gate
+/// dialogue lines and choices.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    QuestComplete { quest: QuestRef },
+    QuestlineComplete { questline: u16 },
+    Flag { flag: String },
+    NotFlag { flag: String },
+    Visited { loc_id: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Quest {
+    pub speaker: String,
+    pub dialog: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quest_id: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quest_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion: Option<QuestCompletion>,
+    /// Journal description; the quest's dialog is used when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Journal hint; generated from `completion` when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+    /// Flags set on the player when this quest is completed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub set_flags: Vec<String>,
+    /// Name other lines of the questline can jump to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    /// Node to continue at after this line instead of the next line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goto: Option<String>,
+    /// The line is skipped unless all of these hold.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<Condition>,
+    /// Options the player picks from to continue past this line.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<DialogChoice>,
+    /// `speaker` parsed by `markup::parse_questlines`.
+    #[serde(skip)]
+    pub speaker_markup: Markup,
+    /// `dialog` parsed by `markup::parse_questlines`.
+    #[serde(skip)]
+    pub dialog_markup: Markup,
+    /// `description` parsed by `markup::parse_questlines`, empty when it's missing.
+    #[serde(skip)]
+    pub description_markup: Markup,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DialogChoice {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub set_flags: Vec<String>,
+    /// Node to continue at; the line after the choice when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goto: Option<String>,
+    /// The choice is hidden unless all of these hold.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<Condition>,
+    /// `text` parsed by `markup::parse_questlines`.
+    #[serde(skip)]
+    pub text_markup: Markup,
+}
+
+/// What the player has to do to complete a quest, tagged by `type` in `questlines.json`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuestCompletion {
+    /// Press Enter next to the object with id `who`.
+    #[serde(alias = "Interact")]
+    Interact { who: String },
+    /// Press Enter next to the NPC with object id `who`.
+    TalkTo { who: String },
+    /// Enter `loc_id`, or get within `radius` tiles of `position` inside it.
+    ReachLocation {
+        loc_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<Vec2>,
+        #[serde(default = "default_reach_radius")]
+        radius: f32,
+    },
+    /// Hold every item in `items`.
+    CollectItem { items: Vec<String> },
+    /// Visit `count` different buildings, optionally only those listed in `loc_ids`.
+    VisitBuildings {
+        count: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc_ids: Option<Vec<String>>,
+    },
+    /// Pick option `answer` (zero based) out of `options`.
+    AnswerQuestion {
+        question: String,
+        options: Vec<String>,
+        answer: usize,
+    },
+    /// Wait `seconds` after the quest starts.
+    Timer { seconds: f32 },
+}
+
+/// How close, in tiles, `ReachLocation` needs the player to get when no radius is given.
+pub const DEFAULT_REACH_RADIUS: f32 = 1.5;
+
+fn default_reach_radius() -> f32 {
+    DEFAULT_REACH_RADIUS
+}
+
+impl QuestCompletion {
+    /// Short description of what the player has to do, for the journal.
+    pub fn hint(&self, strings: &Localization) -> String {
+        match self {
+            QuestCompletion::Interact { who } => strings.format("hint.interact", &[who]),
+            QuestCompletion::TalkTo { who } => strings.format("hint.talk_to", &[who]),
+            QuestCompletion::ReachLocation { loc_id, .. } => {
+                let location = strings
+                    .lookup(&format!("location.{}", loc_id))
+                    .unwrap_or(loc_id);
+                strings.format("hint.reach_location", &[location])
+            }
+            QuestCompletion::CollectItem { items } => {
+                strings.format("hint.collect_item", &[&items.join(", ")])
+            }
+            QuestCompletion::VisitBuildings { count, .. } => {
+                strings.format("hint.visit_buildings", &[&count.to_string()])
+            }
+            QuestCompletion::AnswerQuestion { question, .. } => {
+                strings.format("hint.answer_question", &[question])
+            }
+            QuestCompletion::Timer { seconds } => {
+                strings.format("hint.timer", &[&seconds.to_string()])
+            }
+        }
+    }
+}
+
+impl QuestRef {
+    pub fn new(questline: u16, quest: u16) -> Self {
+        QuestRef { questline, quest }
+    }
+}
+
+impl From<QuestRefRepr> for QuestRef {
+    fn from(repr: QuestRefRepr) -> Self {
+        match repr {
+            QuestRefRepr::Pair(questline, quest) => QuestRef::new(questline, quest),
+            QuestRefRepr::Named { questline, quest } => QuestRef::new(questline, quest),
+        }
+    }
+}
+
+impl From<QuestRef> for (u16, u16) {
+    fn from(quest: QuestRef) -> Self {
+        (quest.questline, quest.quest)
+    }
+}
+
+impl std::fmt::Display for QuestRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.questline, self.quest)
+    }
+}
+
+impl std::fmt::Display for ObjectQuestRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectQuestRef::Quest(quest) => quest.fmt(f),
+            ObjectQuestRef::LegacySum(sum) => write!(f, "{} (legacy sum)", sum),
+        }
+    }
+}
+
+impl Questline {
+    /// The quest a player starts on when this questline begins.
+    pub fn first_quest(&self) -> QuestRef {
+        let quest = self.quests.iter().find_map(|q| q.quest_id).unwrap_or(0);
+        QuestRef::new(self.id, quest)
+    }
+
+    pub fn prerequisites_met(&self, questlines: &[Questline], state: &ClientState) -> bool {
+        let prerequisites = match &self.prerequisites {
+            Some(prerequisites) => prerequisites,
+            None => {
+                return questlines
+                    .iter()
+                    .map(|ql| ql.id)
+                    .filter(|id| *id < self.id)
+                    .max()
+                    .is_none_or(|previous| state.complete_questlines.contains(&previous));
+            }
+        };
+        prerequisites
+            .iter()
+            .all(|condition| condition.is_met(state))
+    }
+}
+
+impl Condition {
+    pub fn is_met(&self, state: &ClientState) -> bool {
+        match self {
+            Condition::QuestComplete { quest } => state.is_quest_complete(*quest),
+            Condition::QuestlineComplete { questline } => {
+                state.complete_questlines.contains(questline)
+            }
+            Condition::Flag { flag } => state.flags.contains(flag),
+            Condition::NotFlag { flag } => !state.flags.contains(flag),
+            Condition::Visited { loc_id } => state.visited_locations.contains(loc_id),
+        }
+    }
+}
+
+impl Quest {
+    pub fn conditions_met(&self, state: &ClientState) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.is_met(state))
+    }
+
+    /// The choices on this line the player can currently pick.
+    pub fn available_choices(&self, state: &ClientState) -> Vec<&DialogChoice> {
+        self.choices
+            .iter()
+            .filter(|choice| choice.conditions.iter().all(|c| c.is_met(state)))
+            .collect()
+    }
+}
+
+impl GameData {
+    /// Builds the game data from the contents of `questlines.json` and `objects.json`,
+    /// parsing the dialogue markup. Objects placed in Tiled maps are added with
+    /// `add_map_objects`.
+    pub fn from_json(questlines: &str, objects: &str) -> Result<Self, ContentError> {
+        let mut game_data = GameData {
+            questlines: serde_json::from_str::<Vec<Questline>>(questlines).map_err(|source| {
+                ContentError::Json {
+                    file: "questlines.json",
+                    source,
+                }
+            })?,
+            object_locations: serde_json::from_str::<Vec<ObjectLocation>>(objects).map_err(
+                |source| ContentError::Json {
+                    file: "objects.json",
+                    source,
+                },
+            )?,
+        };
+        resolve_legacy_quest_refs(&mut game_data.object_locations, &game_data.questlines);
+        let errors = parse_questlines(&mut game_data.questlines, questlines);
+        if !errors.is_empty() {
+            return Err(ContentError::Markup(errors));
+        }
+        Ok(game_data)
+    }
+
+    /// Replaces content text with its translation in `strings`, keeping the original text
+    /// for missing keys.
+    pub fn localize(&mut self, strings: &Localization) -> Result<(), ContentError> {
+        let mut errors = Vec::new();
+        let file = format!("locales/{}.json", strings.locale);
+        for_each_text(&mut self.questlines, |key, text| {
+            if let Some(translation) = strings.lookup(&key) {
+                let is_markup = key.ends_with(".speaker")
+                    || key.ends_with(".dialog")
+                    || key.ends_with(".description")
+                    || key.contains(".choice.");
+                if let Err(message) = Markup::parse(translation) {
+                    if is_markup {
+                        errors.push(MarkupError {
+                            file: file.clone(),
+                            line: 0,
+                            message: format!("{}: {}", key, message),
+                        });
+                        return;
+                    }
+                }
+                *text = translation.to_string();
+            }
+        });
+        // Translations were checked above, so parsing them again can't fail
+        let mut parse =
+            |text: &str, _: &mut Vec<MarkupError>| Markup::parse(text).unwrap_or_default();
+        for questline in self.questlines.iter_mut() {
+            for quest in questline.quests.iter_mut() {
+                parse_quest(quest, &mut parse, &mut errors);
+            }
+        }
+        if !errors.is_empty() {
+            return Err(ContentError::Markup(errors));
+        }
+        Ok(())
+    }
+
+    /// Adds the NPCs and objects placed in a location's Tiled map.
+    pub fn add_map_objects(&mut self, objects: &MapObjects) {
+        self.object_locations
+            .extend(objects.objects.iter().cloned());
+    }
+
+    /// Objects placed in the player's current location that matter for one of their active
+    /// quests.
+    pub fn relevant_objects(&self, state: &ClientState) -> Vec<&ObjectLocation> {
+        self.object_locations
+            .iter()
+            .filter(|obj| obj.is_relevant(state))
+            .collect()
+    }
+}
+
+impl ObjectLocation {
+    /// An object is relevant when it is in the player's location and either has no
+    /// quest restriction or lists one of the player's active quests.
+    pub fn is_relevant(&self, state: &ClientState) -> bool {
+        if self.loc_id != state.location {
+            return false;
+        }
+        match &self.relevant_quest_ids {
+            None => true,
+            Some(ids) => state
+                .active_quests()
+                .into_iter()
+                .any(|quest| ids.contains(&ObjectQuestRef::Quest(quest))),
+        }
+    }
+}
+
+pub fn get_quest_data(questlines: &[Questline], state: &ClientState) -> Option<Quest> {
+    let questline = questlines
+        .iter()
+        .find(|ql| ql.id == state.current_questline_id)?;
+    questline
+        .quests
+        .iter()
+        .find(|q| q.quest_id == Some(state.current_quest_id))
+        .cloned()
+}
+
+/// Brings saves written by older clients up to `SAVE_VERSION`.
+///
+/// Old saves recorded completions as `questline + quest`, which is ambiguous. Questlines
+/// used to be played strictly in order, so every quest matching a legacy sum that lies
+/// before the player's current position was actually completed, as was every questline
+/// before the current one.
+pub fn migrate_save(state: &mut ClientState, questlines: &[Questline]) {
+    if state.save_version >= SAVE_VERSION {
+        return;
+    }
+    let current = state.current_quest();
+    let legacy = std::mem::take(&mut state.legacy_complete_quest_ids);
+    for sum in legacy {
+        for quest in quests_with_sum(questlines, sum).filter(|quest| *quest < current) {
+            state.complete_quest(quest);
+        }
+    }
+    for questline in questlines.iter().filter(|ql| ql.id < current.questline) {
+        if !state.complete_questlines.contains(&questline.id) {
+            state.complete_questlines.push(questline.id);
+        }
+    }
+    state.save_version = SAVE_VERSION;
+}
+
+/// Every quest whose `questline + quest` is `sum`, the way legacy content and saves
+/// identified quests.
+fn quests_with_sum(questlines: &[Questline], sum: u16) -> impl Iterator<Item = QuestRef> + '_ {
+    questlines
+        .iter()
+        .flat_map(|ql| {
+            ql.quests
+                .iter()
+                .filter_map(|q| Some(QuestRef::new(ql.id, q.quest_id?)))
+        })
+        .filter(move |quest| quest.questline as u32 + quest.quest as u32 == sum as u32)
+}
+
+/// Replaces the legacy sums in `objects.json` with every quest matching them. Objects
+/// weren't tied to a player's position, so unlike `migrate_save` all matches are kept.
+/// Sums that match no quest stay as they are for the lint to report.
+fn resolve_legacy_quest_refs(objects: &mut [ObjectLocation], questlines: &[Questline]) {
+    for ids in objects
+        .iter_mut()
+        .filter_map(|o| o.relevant_quest_ids.as_mut())
+    {
+        if !ids
+            .iter()
+            .any(|quest| matches!(quest, ObjectQuestRef::LegacySum(_)))
+        {
+            continue;
+        }
+        let mut resolved = Vec::new();
+        for quest in ids.drain(..) {
+            let matches: Vec<ObjectQuestRef> = match quest {
+                ObjectQuestRef::LegacySum(sum) => quests_with_sum(questlines, sum)
+                    .map(ObjectQuestRef::Quest)
+                    .collect(),
+                ObjectQuestRef::Quest(_) => Vec::new(),
+            };
+            if matches.is_empty() {
+                resolved.push(quest);
+            }
+            for quest in matches {
+                if !resolved.contains(&quest) {
+                    resolved.push(quest);
+                }
+            }
+        }
+        *ids = resolved;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUESTLINES: &str = r#"[
+        {"id": 0, "quests": [
+            {"speaker": "Bob", "dialog": "Hi", "quest_id": 0},
+            {"speaker": "Bob", "dialog": "Find the key", "quest_id": 3}
+        ]},
+        {"id": 1, "quests": [
+            {"speaker": "Amy", "dialog": "Go", "quest_id": 2}
+        ]}
+    ]"#;
+    const OBJECTS: &str = r#"[
+        {"object_id": "chest", "loc_id": "lib", "sprite": {"sprite_map": "objects", "tile_id": 1},
+         "position": [1, 2], "relevant_quest_ids": [[0, 3]]},
+        {"object_id": "sign", "loc_id": "lib", "sprite": {"sprite_map": "objects", "tile_id": 2},
+         "position": [3, 4], "relevant_quest_ids": null},
+        {"object_id": "door", "loc_id": "hall", "sprite": {"sprite_map": "objects", "tile_id": 3},
+         "position": [5, 6], "relevant_quest_ids": null}
+    ]"#;
+
+    fn objects(relevant_quest_ids: &str) -> String {
+        format!(
+            r#"[{{"object_id": "chest", "loc_id": "lib",
+                "sprite": {{"sprite_map": "objects", "tile_id": 1}}, "position": [1, 2],
+                "relevant_quest_ids": {}}}]"#,
+            relevant_quest_ids
+        )
+    }
+
+    fn relevant_quest_ids(objects: &str) -> Vec<ObjectQuestRef> {
+        let game_data = GameData::from_json(QUESTLINES, objects).unwrap();
+        game_data.object_locations[0]
+            .relevant_quest_ids
+            .clone()
+            .unwrap()
+    }
+
+    #[test]
+    fn finds_the_current_quest() {
+        let game_data = GameData::from_json(QUESTLINES, OBJECTS).unwrap();
+        let mut state = ClientState::new("tester");
+        state.current_quest_id = 3;
+        let quest = get_quest_data(&game_data.questlines, &state).unwrap();
+        assert_eq!(quest.dialog, "Find the key");
+        state.current_questline_id = 1;
+        assert!(get_quest_data(&game_data.questlines, &state).is_none());
+    }
+
+    #[test]
+    fn shows_objects_relevant_to_the_current_quest() {
+        let game_data = GameData::from_json(QUESTLINES, OBJECTS).unwrap();
+        let mut state = ClientState::new("tester");
+        state.location = "lib".to_string();
+        let shown = |state: &ClientState| -> Vec<String> {
+            game_data
+                .relevant_objects(state)
+                .iter()
+                .map(|obj| obj.object_id.clone())
+                .collect()
+        };
+        assert_eq!(shown(&state), vec!["sign"]);
+        state.current_quest_id = 3;
+        assert_eq!(shown(&state), vec!["chest", "sign"]);
+    }
+
+    #[test]
+    fn reads_quest_ref_forms() {
+        let ids = relevant_quest_ids(&objects(r#"[[0, 3], {"questline": 1, "quest": 2}]"#));
+        assert_eq!(
+            ids,
+            vec![
+                ObjectQuestRef::Quest(QuestRef::new(0, 3)),
+                ObjectQuestRef::Quest(QuestRef::new(1, 2))
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_legacy_sums_to_every_matching_quest() {
+        let ids = relevant_quest_ids(&objects("[3, [0, 0]]"));
+        assert_eq!(
+            ids,
+            vec![
+                ObjectQuestRef::Quest(QuestRef::new(0, 3)),
+                ObjectQuestRef::Quest(QuestRef::new(1, 2)),
+                ObjectQuestRef::Quest(QuestRef::new(0, 0))
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_legacy_sums_without_a_quest() {
+        let ids = relevant_quest_ids(&objects("[7]"));
+        assert_eq!(ids, vec![ObjectQuestRef::LegacySum(7)]);
+    }
+
+    #[test]
+    fn migrates_legacy_completions_before_the_current_quest() {
+        let game_data = GameData::from_json(QUESTLINES, "[]").unwrap();
+        let mut state = ClientState::new("tester");
+        state.save_version = 0;
+        state.current_questline_id = 1;
+        state.current_quest_id = 2;
+        state.legacy_complete_quest_ids = vec![0, 3];
+
+        migrate_save(&mut state, &game_data.questlines);
+        // Quest 1:2 also sums to 3, but it's the one the player is on
+        assert_eq!(
+            state.complete_quest_ids,
+            vec![QuestRef::new(0, 0), QuestRef::new(0, 3)]
+        );
+        assert_eq!(state.complete_questlines, vec![0]);
+        assert!(state.legacy_complete_quest_ids.is_empty());
+        assert_eq!(state.save_version, SAVE_VERSION);
+    }
+
+    #[test]
+    fn leaves_current_saves_alone() {
+        let game_data = GameData::from_json(QUESTLINES, "[]").unwrap();
+        let mut state = ClientState::new("tester");
+        state.current_questline_id = 1;
+        state.legacy_complete_quest_ids = vec![0];
+
+        migrate_save(&mut state, &game_data.questlines);
+        assert!(state.complete_quest_ids.is_empty());
+        assert!(state.complete_questlines.is_empty());
+    }
+
+    #[test]
+    fn writes_quest_refs_as_pairs() {
+        let json = serde_json::to_string(&QuestRef::new(1, 2)).unwrap();
+        assert_eq!(json, "[1,2]");
+        let json = serde_json::to_string(&ObjectQuestRef::LegacySum(7)).unwrap();
+        assert_eq!(json, "7");
+    }
+}
@@ -0,0 +1,479 @@
+use glam::f32::Vec2;
+use tracing::info;
+
+use crate::{
+    quest_data::{GameData, Quest, QuestCompletion, QuestRef, Questline, NO_QUESTLINE},
+    ClientState,
+};
+
+/// Something the player did that may complete their current quest.
+#[derive(Clone, Copy, Debug)]
+pub enum QuestEvent<'a> {
+    /// The player pressed Enter next to the object with this id.
+    Interact(&'a str),
+    /// The player entered this location.
+    EnterLocation(&'a str),
+    /// The player is standing at this tile position in their current location.
+    Move(Vec2),
+    /// The player picked this option for the current quest's question.
+    Answer(usize),
+    /// This many seconds have passed.
+    Tick(f32),
+}
+
+/// Evaluates quest completions for both the outside and inside scenes.
+pub struct QuestEngine {
+    /// Seconds each active quest has been running. Timers aren't saved, so a `Timer` quest
+    /// starts over when the game is restarted.
+    elapsed: Vec<(QuestRef, f32)>,
+}
+
+impl QuestEngine {
+    pub fn new(state: &ClientState) -> Self {
+        QuestEngine {
+            elapsed: state
+                .active_quests()
+                .into_iter()
+                .map(|quest| (quest, 0.))
+                .collect(),
+        }
+    }
+
+    /// The completion condition of the player's current quest, if it still has to be met.
+    pub fn pending_completion<'a>(
+        &self,
+        game_data: &'a GameData,
+        state: &ClientState,
+    ) -> Option<&'a QuestCompletion> {
+        completion_of(&game_data.questlines, state, state.current_quest())
+    }
+
+    /// Applies `event` to the player's state, completing the quest of every active
+    /// questline it meets the condition of. Returns `true` if it completed their current
+    /// quest; the other questlines move on to their next line when they're focused.
+    pub fn handle(
+        &mut self,
+        game_data: &GameData,
+        state: &mut ClientState,
+        event: QuestEvent,
+    ) -> bool {
+        let active = state.active_quests();
+        self.elapsed.retain(|(quest, _)| active.contains(quest));
+        for quest in &active {
+            if !self.elapsed.iter().any(|(timed, _)| timed == quest) {
+                self.elapsed.push((*quest, 0.));
+            }
+        }
+        match event {
+            QuestEvent::EnterLocation(loc_id) => {
+                if !loc_id.eq_ignore_ascii_case("outside")
+                    && !state.visited_locations.iter().any(|l| l == loc_id)
+                {
+                    state.visited_locations.push(loc_id.to_string());
+                }
+            }
+            QuestEvent::Interact(object_id) => {
+                let item = game_data
+                    .object_locations
+                    .iter()
+                    .find(|obj| obj.object_id.eq_ignore_ascii_case(object_id))
+                    .and_then(|obj| obj.item.clone());
+                if let Some(item) = item {
+                    if !state.inventory.contains(&item) {
+                        info!("Collected '{}'", item);
+                        state.inventory.push(item);
+                    }
+                }
+            }
+            QuestEvent::Tick(dt) => {
+                for (_, elapsed) in &mut self.elapsed {
+                    *elapsed += dt;
+                }
+            }
+            QuestEvent::Move(_) | QuestEvent::Answer(_) => (),
+        }
+
+        let mut completed_current = false;
+        let mut completed_any = false;
+        for quest in active {
+            let current = quest == state.current_quest();
+            // Questions are only asked for the current quest
+            if !current && matches!(event, QuestEvent::Answer(_)) {
+                continue;
+            }
+            let Some(completion) = completion_of(&game_data.questlines, state, quest) else {
+                continue;
+            };
+            if !self.is_met(completion, state, quest, event) {
+                continue;
+            }
+            info!("Quest completed: {}", quest);
+            state.complete_quest(quest);
+            completed_any = true;
+            if let Some(line) = find_quest(&game_data.questlines, quest) {
+                for flag in &line.set_flags {
+                    state.set_flag(flag);
+                }
+            }
+            if current {
+                completed_current = true;
+            } else {
+                advance_dialog_offset(state, quest.questline);
+            }
+        }
+        if completed_any {
+            start_available_questlines(&game_data.questlines, state);
+        }
+        completed_current
+    }
+
+    fn is_met(
+        &self,
+        completion: &QuestCompletion,
+        state: &ClientState,
+        quest: QuestRef,
+        event: QuestEvent,
+    ) -> bool {
+        match (completion, event) {
+            (QuestCompletion::Interact { who }, QuestEvent::Interact(object_id))
+            | (QuestCompletion::TalkTo { who }, QuestEvent::Interact(object_id)) => {
+                who.eq_ignore_ascii_case(object_id)
+            }
+            (
+                QuestCompletion::ReachLocation {
+                    loc_id,
+                    position: None,
+                    ..
+                },
+                _,
+            ) => loc_id == &state.location,
+            (
+                QuestCompletion::ReachLocation {
+                    loc_id,
+                    position: Some(target),
+                    radius,
+                },
+                QuestEvent::Move(pos),
+            ) => loc_id == &state.location && pos.distance(*target) <= *radius,
+            (QuestCompletion::CollectItem { items }, _) => {
+                items.iter().all(|item| state.inventory.contains(item))
+            }
+            (QuestCompletion::VisitBuildings { count, loc_ids }, _) => {
+                let visited = state
+                    .visited_locations
+                    .iter()
+                    .filter(|l| loc_ids.as_ref().is_none_or(|ids| ids.contains(l)))
+                    .count();
+                visited >= *count as usize
+            }
+            (QuestCompletion::AnswerQuestion { answer, .. }, QuestEvent::Answer(choice)) => {
+                if choice != *answer {
+                    info!("Wrong answer for quest {}", state.current_quest());
+                }
+                choice == *answer
+            }
+            (QuestCompletion::Timer { seconds }, _) => self
+                .elapsed
+                .iter()
+                .any(|(timed, elapsed)| *timed == quest && elapsed >= seconds),
+            _ => false,
+        }
+    }
+}
+
+fn find_quest(questlines: &[Questline], quest: QuestRef) -> Option<&Quest> {
+    questlines
+        .iter()
+        .find(|ql| ql.id == quest.questline)?
+        .quests
+        .iter()
+        .find(|q| q.quest_id == Some(quest.quest))
+}
+
+/// The completion condition of `quest`, if it still has to be met.
+fn completion_of<'a>(
+    questlines: &'a [Questline],
+    state: &ClientState,
+    quest: QuestRef,
+) -> Option<&'a QuestCompletion> {
+    if state.is_quest_complete(quest) {
+        return None;
+    }
+    find_quest(questlines, quest)?.completion.as_ref()
+}
+
+/// Moves an active questline that isn't focused on to the line after its quest, the way
+/// the scenes do for the current questline when its quest is completed.
+fn advance_dialog_offset(state: &mut ClientState, questline: u16) {
+    match state
+        .active_dialog_offsets
+        .iter_mut()
+        .find(|(id, _)| *id == questline)
+    {
+        Some((_, offset)) => *offset += 1,
+        None => state.active_dialog_offsets.push((questline, 1)),
+    }
+}
+
+/// Questlines whose prerequisites are met that the player hasn't started, excluding
+/// branches where the player already took another path.
+pub fn available_questlines<'a>(
+    questlines: &'a [Questline],
+    state: &ClientState,
+) -> Vec<&'a Questline> {
+    questlines
+        .iter()
+        .filter(|ql| !state.is_questline_started(ql.id))
+        .filter(|ql| {
+            ql.branch.as_ref().is_none_or(|branch| {
+                !questlines.iter().any(|other| {
+                    other.branch.as_ref() == Some(branch) && state.is_questline_started(other.id)
+                })
+            })
+        })
+        .filter(|ql| ql.prerequisites_met(questlines, state))
+        .collect()
+}
+
+/// Available questlines the player has to pick between, grouped by branch.
+pub fn pending_branches<'a>(
+    questlines: &'a [Questline],
+    state: &ClientState,
+) -> Vec<Vec<&'a Questline>> {
+    let mut branches: Vec<Vec<&Questline>> = Vec::new();
+    for questline in available_questlines(questlines, state) {
+        if questline.branch.is_none() {
+            continue;
+        }
+        match branches
+            .iter_mut()
+            .find(|group| group[0].branch == questline.branch)
+        {
+            Some(group) => group.push(questline),
+            None => branches.push(vec![questline]),
+        }
+    }
+    branches
+}
+
+/// Starts `questline`, making it the current questline if the player has none.
+pub fn start_questline(questlines: &[Questline], state: &mut ClientState, questline: u16) {
+    let questline = match questlines.iter().find(|ql| ql.id == questline) {
+        Some(questline) => questline,
+        None => return,
+    };
+    if state.is_questline_started(questline.id) {
+        return;
+    }
+    info!("Questline started: {}", questline.id);
+    let first = questline.first_quest();
+    if state.current_questline_id == NO_QUESTLINE {
+        state.current_questline_id = first.questline;
+        state.current_quest_id = first.quest;
+        state.dialog_offset = 0;
+    } else {
+        state.active_questlines.push(first);
+    }
+}
+
+/// Starts every available questline that isn't part of a branch.
+pub fn start_available_questlines(questlines: &[Questline], state: &mut ClientState) {
+    let available: Vec<u16> = available_questlines(questlines, state)
+        .iter()
+        .filter(|ql| ql.branch.is_none())
+        .map(|ql| ql.id)
+        .collect();
+    for questline in available {
+        start_questline(questlines, state, questline);
+    }
+}
+
+/// Makes the active `questline` the current one, putting the current one back in the
+/// active list. Each questline keeps the dialog offset it was at.
+pub fn focus_questline(state: &mut ClientState, questline: u16) {
+    if state.current_questline_id == questline {
+        return;
+    }
+    let index = match state
+        .active_questlines
+        .iter()
+        .position(|q| q.questline == questline)
+    {
+        Some(index) => index,
+        None => return,
+    };
+    let focused = state.active_questlines.remove(index);
+    if state.current_questline_id != NO_QUESTLINE {
+        let current = state.current_quest();
+        state.active_questlines.push(current);
+        state
+            .active_dialog_offsets
+            .push((current.questline, state.dialog_offset));
+    }
+    state.current_questline_id = focused.questline;
+    state.current_quest_id = focused.quest;
+    state.dialog_offset = match state
+        .active_dialog_offsets
+        .iter()
+        .position(|(questline, _)| *questline == focused.questline)
+    {
+        Some(index) => state.active_dialog_offsets.remove(index).1,
+        None => 0,
+    };
+}
+
+/// Marks the current questline complete, starts whatever it unlocked and moves on to the
+/// next active questline.
+pub fn finish_questline(questlines: &[Questline], state: &mut ClientState) {
+    let finished = state.current_questline_id;
+    info!("Questline completed: {}", finished);
+    if !state.complete_questlines.contains(&finished) {
+        state.complete_questlines.push(finished);
+    }
+    state.current_questline_id = NO_QUESTLINE;
+    state.current_quest_id = 0;
+    state.dialog_offset = 0;
+    start_available_questlines(questlines, state);
+    if state.current_questline_id == NO_QUESTLINE {
+        if let Some(next) = state.active_questlines.iter().map(|q| q.questline).min() {
+            focus_questline(state, next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUESTLINES: &str = r#"[
+        {"id": 0, "quests": [
+            {"speaker": "Bob", "dialog": "Hi", "quest_id": 0, "set_flags": ["met_bob"],
+             "completion": {"type": "talk_to", "who": "bob"}},
+            {"speaker": "Bob", "dialog": "Wait", "quest_id": 1,
+             "completion": {"type": "timer", "seconds": 2}}
+        ]},
+        {"id": 1, "prerequisites": [{"type": "flag", "flag": "met_bob"}], "quests": [
+            {"speaker": "Amy", "dialog": "Look around", "quest_id": 0,
+             "completion": {"type": "visit_buildings", "count": 2}}
+        ]}
+    ]"#;
+
+    fn game_data() -> GameData {
+        GameData::from_json(QUESTLINES, "[]").unwrap()
+    }
+
+    #[test]
+    fn talking_completes_the_quest_and_starts_what_it_unlocks() {
+        let game_data = game_data();
+        let mut state = ClientState::new("tester");
+        let mut engine = QuestEngine::new(&state);
+
+        assert!(!engine.handle(&game_data, &mut state, QuestEvent::Interact("amy")));
+        assert!(engine.handle(&game_data, &mut state, QuestEvent::Interact("Bob")));
+        assert!(state.is_quest_complete(QuestRef::new(0, 0)));
+        assert_eq!(state.flags, vec!["met_bob"]);
+        assert_eq!(state.active_questlines, vec![QuestRef::new(1, 0)]);
+        assert!(engine.pending_completion(&game_data, &state).is_none());
+    }
+
+    #[test]
+    fn timers_count_from_when_the_quest_starts() {
+        let game_data = game_data();
+        let mut state = ClientState::new("tester");
+        let mut engine = QuestEngine::new(&state);
+        engine.handle(&game_data, &mut state, QuestEvent::Tick(5.));
+
+        state.current_quest_id = 1;
+        assert!(!engine.handle(&game_data, &mut state, QuestEvent::Tick(1.5)));
+        assert!(engine.handle(&game_data, &mut state, QuestEvent::Tick(1.)));
+        assert!(state.is_quest_complete(QuestRef::new(0, 1)));
+    }
+
+    #[test]
+    fn visiting_buildings_records_each_once() {
+        let game_data = game_data();
+        let mut state = ClientState::new("tester");
+        state.current_questline_id = 1;
+        let mut engine = QuestEngine::new(&state);
+
+        assert!(!engine.handle(&game_data, &mut state, QuestEvent::EnterLocation("lib")));
+        assert!(!engine.handle(&game_data, &mut state, QuestEvent::EnterLocation("outside")));
+        assert!(!engine.handle(&game_data, &mut state, QuestEvent::EnterLocation("lib")));
+        assert!(engine.handle(&game_data, &mut state, QuestEvent::EnterLocation("hall")));
+        assert_eq!(state.visited_locations, vec!["lib", "hall"]);
+    }
+
+    #[test]
+    fn completes_quests_of_questlines_that_arent_focused() {
+        let game_data = game_data();
+        let mut state = ClientState::new("tester");
+        state.active_questlines.push(QuestRef::new(1, 0));
+        let mut engine = QuestEngine::new(&state);
+
+        engine.handle(&game_data, &mut state, QuestEvent::EnterLocation("lib"));
+        assert!(!engine.handle(&game_data, &mut state, QuestEvent::EnterLocation("hall")));
+        assert!(state.is_quest_complete(QuestRef::new(1, 0)));
+        assert_eq!(state.active_dialog_offsets, vec![(1, 1)]);
+
+        assert!(engine.handle(&game_data, &mut state, QuestEvent::Interact("bob")));
+        assert!(state.is_quest_complete(QuestRef::new(0, 0)));
+        assert_eq!(state.dialog_offset, 0);
+    }
+
+    #[test]
+    fn timers_run_in_every_active_questline() {
+        let questlines = r#"[
+            {"id": 0, "quests": [{"speaker": "Bob", "dialog": "Wait", "quest_id": 0,
+                                  "completion": {"type": "timer", "seconds": 1}}]},
+            {"id": 1, "quests": [{"speaker": "Amy", "dialog": "Wait", "quest_id": 0,
+                                  "completion": {"type": "timer", "seconds": 2}}]}
+        ]"#;
+        let game_data = GameData::from_json(questlines, "[]").unwrap();
+        let mut state = ClientState::new("tester");
+        state.active_questlines.push(QuestRef::new(1, 0));
+        let mut engine = QuestEngine::new(&state);
+
+        assert!(engine.handle(&game_data, &mut state, QuestEvent::Tick(1.5)));
+        assert!(!state.is_quest_complete(QuestRef::new(1, 0)));
+        assert!(!engine.handle(&game_data, &mut state, QuestEvent::Tick(1.)));
+        assert!(state.is_quest_complete(QuestRef::new(1, 0)));
+    }
+
+    fn state_with_two_questlines() -> ClientState {
+        let mut state = ClientState::new("tester");
+        state.current_questline_id = 0;
+        state.current_quest_id = 1;
+        state.dialog_offset = 4;
+        state.active_questlines.push(QuestRef::new(1, 0));
+        state
+    }
+
+    #[test]
+    fn focus_keeps_each_questlines_dialog_offset() {
+        let mut state = state_with_two_questlines();
+        focus_questline(&mut state, 1);
+        assert_eq!(state.current_quest(), QuestRef::new(1, 0));
+        assert_eq!(state.dialog_offset, 0);
+        state.dialog_offset = 2;
+
+        focus_questline(&mut state, 0);
+        assert_eq!(state.current_quest(), QuestRef::new(0, 1));
+        assert_eq!(state.dialog_offset, 4);
+        assert_eq!(state.active_questlines, vec![QuestRef::new(1, 0)]);
+        assert_eq!(state.active_dialog_offsets, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn finish_resumes_the_next_questline_where_it_was_left() {
+        let mut state = state_with_two_questlines();
+        focus_questline(&mut state, 1);
+        state.dialog_offset = 3;
+        focus_questline(&mut state, 0);
+
+        finish_questline(&[], &mut state);
+        assert!(state.complete_questlines.contains(&0));
+        assert_eq!(state.current_quest(), QuestRef::new(1, 0));
+        assert_eq!(state.dialog_offset, 3);
+        assert!(state.active_dialog_offsets.is_empty());
+    }
+}
@@ -0,0 +1,560 @@
+//! Draws the quest content as a graph for Graphviz (DOT) or Mermaid: each questline is a
+//! cluster of its quests, linked by the order they're played in, dialogue jumps and
+//! prerequisites, and to the objects, locations, items and flags they refer to.
+
+use std::{collections::HashMap, fmt::Write, str::FromStr};
+
+use crate::quest_data::{Condition, GameData, QuestCompletion, Questline};
+
+/// Longest question or choice text shown before it's cut short.
+const MAX_TEXT: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => Err(format!(
+                "unknown format '{}', expected 'dot' or 'mermaid'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum NodeKind {
+    /// Where a questline starts, and what its prerequisites point to.
+    Start,
+    Quest,
+    Object,
+    Location,
+    Item,
+    Flag,
+}
+
+struct Node {
+    id: String,
+    kind: NodeKind,
+    label: Vec<String>,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    label: Option<String>,
+    /// References to things the quest needs rather than the order quests are played in.
+    dashed: bool,
+}
+
+struct Cluster {
+    id: String,
+    label: String,
+    nodes: Vec<Node>,
+}
+
+/// Questlines with their quests, and everything they link to.
+#[derive(Default)]
+pub struct QuestGraph {
+    clusters: Vec<Cluster>,
+    /// Objects, locations, items and flags, shared between questlines.
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    shared_ids: HashMap<(NodeKind, String), String>,
+}
+
+impl QuestGraph {
+    pub fn build(game_data: &GameData) -> Self {
+        let mut graph = QuestGraph::default();
+        for questline in &game_data.questlines {
+            graph.add_questline(game_data, questline);
+        }
+        for questline in &game_data.questlines {
+            graph.add_prerequisites(&game_data.questlines, questline);
+        }
+        graph
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    fn add_questline(&mut self, game_data: &GameData, questline: &Questline) {
+        let mut label = format!("Questline {}", questline.id);
+        if let Some(name) = &questline.name {
+            write!(label, ": {}", name).unwrap();
+        }
+        if let Some(branch) = &questline.branch {
+            write!(label, " (branch {})", branch).unwrap();
+        }
+        let start = start_node(questline.id);
+        let mut nodes = vec![Node {
+            id: start.clone(),
+            kind: NodeKind::Start,
+            label: vec![String::from("start")],
+        }];
+
+        let mut previous = start;
+        for quest in &questline.quests {
+            let Some(quest_id) = quest.quest_id else {
+                continue;
+            };
+            let id = quest_node(questline.id, quest_id);
+            let title = quest.quest_name.as_deref().unwrap_or(&quest.speaker);
+            let mut label = vec![format!("{}: {}", quest_id, title)];
+            if let Some(completion) = &quest.completion {
+                label.push(describe(completion));
+                self.add_completion(game_data, &id, completion);
+            }
+            for flag in &quest.set_flags {
+                let flag = self.shared(NodeKind::Flag, flag);
+                self.edge(&id, &flag, Some("sets"), true);
+            }
+            nodes.push(Node {
+                id: id.clone(),
+                kind: NodeKind::Quest,
+                label,
+            });
+            self.edge(&previous, &id, None, false);
+            previous = id;
+        }
+
+        // Jumps and choices that lead out of the quest they're in
+        for (index, line) in questline.quests.iter().enumerate() {
+            let from = containing_quest(questline, index);
+            let choices = line
+                .choices
+                .iter()
+                .filter_map(|choice| Some((choice.goto.as_ref()?, shorten(&choice.text))));
+            let jumps = line
+                .goto
+                .iter()
+                .map(|node| (node, String::from("goto")))
+                .chain(choices);
+            for (node, label) in jumps {
+                let Some(target) = questline
+                    .quests
+                    .iter()
+                    .position(|l| l.node.as_ref() == Some(node))
+                else {
+                    continue;
+                };
+                let to = containing_quest(questline, target);
+                if to != from {
+                    self.edge(&from, &to, Some(&label), false);
+                }
+            }
+            for flag in line.choices.iter().flat_map(|choice| &choice.set_flags) {
+                let flag = self.shared(NodeKind::Flag, flag);
+                self.edge(&from, &flag, Some("sets"), true);
+            }
+        }
+
+        self.clusters.push(Cluster {
+            id: format!("cluster_ql{}", questline.id),
+            label,
+            nodes,
+        });
+    }
+
+    fn add_completion(&mut self, game_data: &GameData, quest: &str, completion: &QuestCompletion) {
+        match completion {
+            QuestCompletion::Interact { who } | QuestCompletion::TalkTo { who } => {
+                let object = self.object(game_data, who);
+                self.edge(quest, &object, None, true);
+            }
+            QuestCompletion::ReachLocation { loc_id, .. } => {
+                let location = self.shared(NodeKind::Location, loc_id);
+                self.edge(quest, &location, None, true);
+            }
+            QuestCompletion::CollectItem { items } => {
+                for item in items {
+                    // Point at the objects that give the item, if the content has any
+                    let givers: Vec<&str> = game_data
+                        .object_locations
+                        .iter()
+                        .filter(|obj| obj.item.as_ref() == Some(item))
+                        .map(|obj| obj.object_id.as_str())
+                        .collect();
+                    if givers.is_empty() {
+                        let item_node = self.shared(NodeKind::Item, item);
+                        self.edge(quest, &item_node, None, true);
+                    }
+                    for giver in givers {
+                        let object = self.object(game_data, giver);
+                        self.edge(quest, &object, Some(item), true);
+                    }
+                }
+            }
+            QuestCompletion::VisitBuildings {
+                loc_ids: Some(loc_ids),
+                ..
+            } => {
+                for loc_id in loc_ids {
+                    let location = self.shared(NodeKind::Location, loc_id);
+                    self.edge(quest, &location, None, true);
+                }
+            }
+            QuestCompletion::VisitBuildings { loc_ids: None, .. }
+            | QuestCompletion::AnswerQuestion { .. }
+            | QuestCompletion::Timer { .. } => (),
+        }
+    }
+
+    fn add_prerequisites(&mut self, questlines: &[Questline], questline: &Questline) {
+        let to = start_node(questline.id);
+        let Some(prerequisites) = &questline.prerequisites else {
+            // Without prerequisites a questline follows the one with the next lower id
+            let previous = questlines
+                .iter()
+                .filter(|ql| ql.id < questline.id)
+                .max_by_key(|ql| ql.id);
+            if let Some(previous) = previous {
+                self.edge(&last_node(previous), &to, Some("then"), false);
+            }
+            return;
+        };
+        for condition in prerequisites {
+            match condition {
+                Condition::QuestComplete { quest } => {
+                    let from = quest_node(quest.questline, quest.quest);
+                    self.edge(&from, &to, Some("unlocks"), false);
+                }
+                Condition::QuestlineComplete { questline: id } => {
+                    let from = match questlines.iter().find(|ql| ql.id == *id) {
+                        Some(previous) => last_node(previous),
+                        None => start_node(*id),
+                    };
+                    self.edge(&from, &to, Some("unlocks"), false);
+                }
+                Condition::Flag { flag } => {
+                    let flag = self.shared(NodeKind::Flag, flag);
+                    self.edge(&flag, &to, Some("requires"), true);
+                }
+                Condition::NotFlag { flag } => {
+                    let flag = self.shared(NodeKind::Flag, flag);
+                    self.edge(&flag, &to, Some("requires not"), true);
+                }
+                Condition::Visited { loc_id } => {
+                    let location = self.shared(NodeKind::Location, loc_id);
+                    self.edge(&location, &to, Some("visited"), true);
+                }
+            }
+        }
+    }
+
+    /// The node for the object `object_id`, linked to the locations it's placed in.
+    fn object(&mut self, game_data: &GameData, object_id: &str) -> String {
+        let key = (NodeKind::Object, object_id.to_string());
+        if let Some(id) = self.shared_ids.get(&key) {
+            return id.clone();
+        }
+        let id = self.shared(NodeKind::Object, object_id);
+        let mut locations: Vec<&str> = game_data
+            .object_locations
+            .iter()
+            .filter(|obj| obj.object_id.eq_ignore_ascii_case(object_id))
+            .map(|obj| obj.loc_id.as_str())
+            .collect();
+        locations.sort_unstable();
+        locations.dedup();
+        for loc_id in locations {
+            let location = self.shared(NodeKind::Location, loc_id);
+            self.edge(&id, &location, None, true);
+        }
+        id
+    }
+
+    /// The node for `name`, added the first time it's referred to.
+    fn shared(&mut self, kind: NodeKind, name: &str) -> String {
+        let key = (kind, name.to_string());
+        if let Some(id) = self.shared_ids.get(&key) {
+            return id.clone();
+        }
+        let prefix = match kind {
+            NodeKind::Object => "obj",
+            NodeKind::Location => "loc",
+            NodeKind::Item => "item",
+            NodeKind::Flag => "flag",
+            NodeKind::Start | NodeKind::Quest => unreachable!("Quests aren't shared"),
+        };
+        let id = format!("{}{}", prefix, self.shared_ids.len());
+        self.shared_ids.insert(key, id.clone());
+        self.nodes.push(Node {
+            id: id.clone(),
+            kind,
+            label: vec![name.to_string()],
+        });
+        id
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>, dashed: bool) {
+        self.edges.push(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label: label.map(str::to_string),
+            dashed,
+        });
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph quests {\n");
+        let dot_node = |out: &mut String, node: &Node, indent: &str| {
+            let shape = match node.kind {
+                NodeKind::Start => "circle",
+                NodeKind::Quest => "box",
+                NodeKind::Object => "ellipse",
+                NodeKind::Location => "hexagon",
+                NodeKind::Item => "parallelogram",
+                NodeKind::Flag => "note",
+            };
+            let label: Vec<String> = node.label.iter().map(|line| dot_escape(line)).collect();
+            writeln!(
+                out,
+                "{}{} [shape={}, label=\"{}\"];",
+                indent,
+                node.id,
+                shape,
+                label.join("\\n")
+            )
+            .unwrap();
+        };
+        for cluster in &self.clusters {
+            writeln!(out, "    subgraph {} {{", cluster.id).unwrap();
+            writeln!(out, "        label=\"{}\";", dot_escape(&cluster.label)).unwrap();
+            for node in &cluster.nodes {
+                dot_node(&mut out, node, "        ");
+            }
+            out.push_str("    }\n");
+        }
+        for node in &self.nodes {
+            dot_node(&mut out, node, "    ");
+        }
+        for edge in &self.edges {
+            let mut attributes = Vec::new();
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label=\"{}\"", dot_escape(label)));
+            }
+            if edge.dashed {
+                attributes.push(String::from("style=dashed"));
+            }
+            write!(out, "    {} -> {}", edge.from, edge.to).unwrap();
+            if !attributes.is_empty() {
+                write!(out, " [{}]", attributes.join(", ")).unwrap();
+            }
+            out.push_str(";\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        let mermaid_node = |out: &mut String, node: &Node, indent: &str| {
+            let (open, close) = match node.kind {
+                NodeKind::Start => ("((", "))"),
+                NodeKind::Quest => ("[", "]"),
+                NodeKind::Object => ("([", "])"),
+                NodeKind::Location => ("{{", "}}"),
+                NodeKind::Item => ("[/", "/]"),
+                NodeKind::Flag => (">", "]"),
+            };
+            let label: Vec<String> = node.label.iter().map(|line| mermaid_escape(line)).collect();
+            writeln!(
+                out,
+                "{}{}{}\"{}\"{}",
+                indent,
+                node.id,
+                open,
+                label.join("<br>"),
+                close
+            )
+            .unwrap();
+        };
+        for cluster in &self.clusters {
+            writeln!(
+                out,
+                "    subgraph {} [\"{}\"]",
+                cluster.id,
+                mermaid_escape(&cluster.label)
+            )
+            .unwrap();
+            for node in &cluster.nodes {
+                mermaid_node(&mut out, node, "        ");
+            }
+            out.push_str("    end\n");
+        }
+        for node in &self.nodes {
+            mermaid_node(&mut out, node, "    ");
+        }
+        for edge in &self.edges {
+            let arrow = if edge.dashed { "-.->" } else { "-->" };
+            match &edge.label {
+                Some(label) => writeln!(
+                    out,
+                    "    {} {}|\"{}\"| {}",
+                    edge.from,
+                    arrow,
+                    mermaid_escape(label),
+                    edge.to
+                ),
+                None => writeln!(out, "    {} {} {}", edge.from, arrow, edge.to),
+            }
+            .unwrap();
+        }
+        out
+    }
+}
+
+fn start_node(questline: u16) -> String {
+    format!("ql{}", questline)
+}
+
+fn quest_node(questline: u16, quest: u16) -> String {
+    format!("q{}_{}", questline, quest)
+}
+
+/// The node of the quest the line at `index` belongs to: the closest quest at or before it.
+fn containing_quest(questline: &Questline, index: usize) -> String {
+    questline.quests[..=index]
+        .iter()
+        .rev()
+        .find_map(|quest| quest.quest_id)
+        .map(|quest| quest_node(questline.id, quest))
+        .unwrap_or_else(|| start_node(questline.id))
+}
+
+/// The node completing `questline` leads on from.
+fn last_node(questline: &Questline) -> String {
+    questline
+        .quests
+        .iter()
+        .rev()
+        .find_map(|quest| quest.quest_id)
+        .map(|quest| quest_node(questline.id, quest))
+        .unwrap_or_else(|| start_node(questline.id))
+}
+
+/// What the player has to do, in a few words.
+fn describe(completion: &QuestCompletion) -> String {
+    match completion {
+        QuestCompletion::Interact { who } => format!("interact with {}", who),
+        QuestCompletion::TalkTo { who } => format!("talk to {}", who),
+        QuestCompletion::ReachLocation { loc_id, .. } => format!("reach {}", loc_id),
+        QuestCompletion::CollectItem { items } => format!("collect {}", items.join(", ")),
+        QuestCompletion::VisitBuildings { count, .. } => format!("visit {} buildings", count),
+        QuestCompletion::AnswerQuestion { question, .. } => {
+            format!("answer \"{}\"", shorten(question))
+        }
+        QuestCompletion::Timer { seconds } => format!("wait {}s", seconds),
+    }
+}
+
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(MAX_TEXT) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUESTLINES: &str = r#"[
+        {"id": 0, "name": "Intro", "quests": [
+            {"speaker": "Bob", "dialog": "Hi", "quest_id": 0, "quest_name": "Meet \"Bob\"",
+             "completion": {"type": "talk_to", "who": "bob"}},
+            {"speaker": "Bob", "dialog": "Find the key", "quest_id": 1, "set_flags": ["keyed"],
+             "completion": {"type": "collect_item", "items": ["key"]}}
+        ]},
+        {"id": 1, "quests": [
+            {"speaker": "Amy", "dialog": "Go", "quest_id": 0,
+             "completion": {"type": "reach_location", "loc_id": "lib"}}
+        ]},
+        {"id": 2, "branch": "club", "prerequisites": [{"type": "flag", "flag": "keyed"}],
+         "quests": [{"speaker": "Cat", "dialog": "Wait", "quest_id": 0,
+                     "completion": {"type": "timer", "seconds": 3}}]}
+    ]"#;
+    const OBJECTS: &str = r#"[
+        {"object_id": "bob", "loc_id": "hall", "sprite": {"sprite_map": "npcs", "tile_id": 1},
+         "position": [1, 2], "relevant_quest_ids": null},
+        {"object_id": "drawer", "loc_id": "lib", "sprite": {"sprite_map": "objects", "tile_id": 2},
+         "position": [3, 4], "relevant_quest_ids": null, "item": "key"}
+    ]"#;
+
+    fn render(format: GraphFormat) -> String {
+        let game_data = GameData::from_json(QUESTLINES, OBJECTS).unwrap();
+        QuestGraph::build(&game_data).render(format)
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!("dot".parse(), Ok(GraphFormat::Dot));
+        assert_eq!("mermaid".parse(), Ok(GraphFormat::Mermaid));
+        assert!("svg".parse::<GraphFormat>().is_err());
+    }
+
+    #[test]
+    fn renders_dot() {
+        let dot = render(GraphFormat::Dot);
+        assert!(dot.starts_with("digraph quests {\n"));
+        assert!(dot.ends_with("}\n"));
+        let expected = [
+            "    subgraph cluster_ql0 {\n        label=\"Questline 0: Intro\";\n",
+            "        label=\"Questline 2 (branch club)\";\n",
+            "        q0_0 [shape=box, label=\"0: Meet \\\"Bob\\\"\\ntalk to bob\"];\n",
+            "    ql0 -> q0_0;\n",
+            "    q0_0 -> q0_1;\n",
+            "    q0_0 -> obj0 [style=dashed];\n",
+            "    obj0 -> loc1 [style=dashed];\n",
+            "    q0_1 -> obj2 [label=\"key\", style=dashed];\n",
+            "    q0_1 -> flag4 [label=\"sets\", style=dashed];\n",
+            "    q0_1 -> ql1 [label=\"then\"];\n",
+            "    flag4 -> ql2 [label=\"requires\", style=dashed];\n",
+        ];
+        for line in expected {
+            assert!(dot.contains(line), "{:?} not in\n{}", line, dot);
+        }
+        // Objects and locations are shared rather than added for every reference
+        assert_eq!(dot.matches("[shape=hexagon, label=\"lib\"]").count(), 1);
+    }
+
+    #[test]
+    fn renders_mermaid() {
+        let mermaid = render(GraphFormat::Mermaid);
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        let expected = [
+            "    subgraph cluster_ql0 [\"Questline 0: Intro\"]\n",
+            "        ql0((\"start\"))\n",
+            "        q0_0[\"0: Meet #quot;Bob#quot;<br>talk to bob\"]\n",
+            "    end\n",
+            "    obj0([\"bob\"])\n",
+            "    q1_0 -.-> loc3\n",
+            "    q0_1 -.->|\"sets\"| flag4\n",
+            "    q0_1 -->|\"then\"| ql1\n",
+        ];
+        for line in expected {
+            assert!(mermaid.contains(line), "{:?} not in\n{}", line, mermaid);
+        }
+    }
+}
@@ -28,21 +28,21 @@ pub async fn auth(stream: Arc<Mutex<SslStream<TcpStream>>>, peer: u8) -> Option<
     info!("Client '{}': {}", peer, auth_msg);
     let auth = serde_json::from_str::<ClientAuth>(&auth_msg);
     drop(auth_msg);
-    if auth.is_err() {
-        error!(
-            "Client '{}': Client's auth packet couldn't be deserialized: {}",
-            peer,
-            &auth.unwrap_err()
-        );
-        return None;
-    }
-    let auth = auth.unwrap();
+    let auth = match auth {
+        Ok(auth) => auth,
+        Err(err) => {
+            error!(
+                "Client '{}': Client's auth packet couldn't be deserialized: {}",
+                peer, &err
+            );
+            return None;
+        }
+    };
     info!("Client '{}': Received client authentication", peer);
     info!("Client '{}': Client username: {}", peer, &auth.username);
     info!("Client '{}': Getting user store", peer);
     let user_data = get_user_file(auth.username.clone()).await;
-    if user_data.is_some() {
-        let user = user_data.unwrap();
+    if let Some(user) = user_data {
         if user.pass_hash == auth.pass_hash {
             return Some(user);
         } else {
@@ -62,10 +62,9 @@ async fn get_user_file(username: String) -> Option<UserStore> {
     file_path.push_str(&username);
     file_path.push_str(".gvdata");
     let file_path = Path::new(&file_path);
-    if file_path.try_exists().is_ok() && file_path.try_exists().unwrap() {
-        let file_data = fs::read_to_string(file_path).await;
-        if file_data.is_ok() {
-            return Some(serde_json::from_str::<UserStore>(&file_data.unwrap()).unwrap());
+    if file_path.try_exists().unwrap_or(false) {
+        if let Ok(file_data) = fs::read_to_string(file_path).await {
+            return Some(serde_json::from_str::<UserStore>(&file_data).unwrap());
         }
     }
     None
@@ -14,18 +14,21 @@ use tracing::{error, info};
 
 use crate::client_auth;
 
-pub async fn handle_client(peer: u8, stream: SslStream<TcpStream>) {
+pub async fn handle_client(peer: u8, stream: SslStream<TcpStream>, hello: &str) {
     let stream = Arc::new(Mutex::new(stream));
 
     info!("New Socket connection: {}", peer);
+    if let Err(err) = write_flush(stream.clone(), hello.to_string()).await {
+        error!("Client '{}': Couldn't send hello: {}", peer, err);
+        return;
+    }
 
     // Receive Client Auth Packet
     let user_store = client_auth::auth(stream.clone(), peer).await;
-    if user_store.is_none() {
+    let Some(mut user_store) = user_store else {
         return;
-    }
-    let mut user_store = user_store.unwrap();
-    if user_store.state.authenticated == false {
+    };
+    if !user_store.state.authenticated {
         return;
     }
     let client_state = user_store.state.clone();
@@ -40,9 +43,7 @@ pub async fn handle_client(peer: u8, stream: SslStream<TcpStream>) {
     }
     info!("Client '{}': Client state sent", peer);
     loop {
-        let closure = read_stream(stream.clone()).await;
-        if closure.is_ok() {
-            let closure_ser = closure.unwrap();
+        if let Ok(closure_ser) = read_stream(stream.clone()).await {
             let closure = serde_json::from_str::<ClientState>(&closure_ser).unwrap();
             info!("Client '{}': Exiting \"{}\"", peer, &closure_ser);
             user_store.state = closure;
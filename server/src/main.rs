@@ -1,11 +1,19 @@
 pub mod client_auth;
 pub mod handle_client;
+pub mod pack_server;
 
 use crate::handle_client::handle_client;
+use crate::pack_server::{serve_pack, DEFAULT_PACK_PORT, PACK_DIR_VAR, PACK_PORT_VAR};
+use common::{
+    assets::{AssetKind, AssetSource, SERVER_HOST},
+    ServerHello,
+};
 use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
 use std::net::TcpListener;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::error;
+use std::env;
+use tracing::{error, info};
 
 #[tokio::main]
 async fn main() {
@@ -28,6 +36,33 @@ async fn main() {
     acceptor.check_private_key().unwrap();
     let acceptor = Arc::new(acceptor.build());
 
+    // Serve a packed copy of the assets when asked to
+    let pack_source = match env::var(PACK_DIR_VAR) {
+        Ok(dir) => {
+            let port = env::var(PACK_PORT_VAR)
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(DEFAULT_PACK_PORT);
+            tokio::spawn(serve_pack(PathBuf::from(dir), port));
+            Some(AssetSource {
+                kind: AssetKind::Pack,
+                url: format!("http://{}:{}/", SERVER_HOST, port),
+                ..Default::default()
+            })
+        }
+        Err(_) => None,
+    };
+
+    // Assets advertised to clients, set with the same variables the client reads
+    let hello = ServerHello {
+        assets: AssetSource::from_env(&pack_source.clone().unwrap_or_default()).or(pack_source),
+    };
+    match &hello.assets {
+        Some(assets) => info!("Advertising assets from {} ({})", assets.url, assets.reference()),
+        None => info!("Not advertising an asset source"),
+    }
+    let hello = Arc::new(serde_json::to_string(&hello).unwrap());
+
     let listener = TcpListener::bind("0.0.0.0:3000").unwrap();
 
     // Setup Master Broadcast Channel
@@ -38,13 +73,14 @@ async fn main() {
         match stream {
             Ok(stream) => {
                 let acceptor = acceptor.clone();
+                let hello = hello.clone();
                 // let mb = master_broadcast.clone();
                 tokio::spawn(async move {
                     let stream = acceptor.accept(stream).unwrap();
                     let mut peer_id = [0u8; 1];
                     let _ = openssl::rand::rand_bytes(&mut peer_id);
                     let peer_id = peer_id[0];
-                    handle_client(peer_id, stream).await;
+                    handle_client(peer_id, stream, &hello).await;
                 });
             }
             Err(_) => { /* connection failed */ }
@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+
+use common::assets::{decode_url_path, is_safe_path};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{error, info};
+
+/// Environment variable naming a directory of assets packed with `gv-pack` to serve.
+pub const PACK_DIR_VAR: &str = "GV_PACK_DIR";
+/// Environment variable setting the port packs are served on.
+pub const PACK_PORT_VAR: &str = "GV_PACK_PORT";
+pub const DEFAULT_PACK_PORT: u16 = 3001;
+
+/// Serves the files of an asset pack over plain HTTP. Clients check every file against the
+/// signed manifest, so the connection doesn't need to be trusted.
+pub async fn serve_pack(dir: PathBuf, port: u16) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Couldn't serve assets on port {}: {}", port, err);
+            return;
+        }
+    };
+    info!("Serving assets from {} on port {}", dir.display(), port);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let dir = dir.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = handle_request(stream, &dir).await {
+                        error!("Asset request failed: {}", err);
+                    }
+                });
+            }
+            Err(err) => error!("{}", err),
+        }
+    }
+}
+
+async fn handle_request(mut stream: TcpStream, dir: &Path) -> std::io::Result<()> {
+    // Only the request line matters, and it comes first
+    let mut buf = [0u8; 2048];
+    let len = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let target = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => target,
+        _ => return respond(&mut stream, "405 Method Not Allowed", &[]).await,
+    };
+    // Paths are percent-encoded, so `..` can only be checked for once they're decoded
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let file = match decode_url_path(path.trim_start_matches('/')) {
+        Some(file) if is_safe_path(&file) => file,
+        _ => return respond(&mut stream, "404 Not Found", &[]).await,
+    };
+    match fs::read(dir.join(file)).await {
+        Ok(data) => respond(&mut stream, "200 OK", &data).await,
+        Err(_) => respond(&mut stream, "404 Not Found", &[]).await,
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &[u8]) -> std::io::Result<()> {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nContent-Type: application/octet-stream\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}